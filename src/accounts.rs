@@ -0,0 +1,463 @@
+use solana_program::{
+    account_info::{
+        AccountInfo,
+        next_account_info,
+    },
+    instruction::AccountMeta,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::error::McPayError;
+use crate::validation::{
+    assert_address,
+    assert_owner,
+    assert_signer,
+    assert_writable,
+};
+
+// constraints an account is declared with:
+// signer, mut, address = <key> => <McPayError>, owner = <program> => <McPayError>
+macro_rules! account_constraints {
+    ($account:ident;) => {};
+    ($account:ident; signer $(, $($rest:tt)*)?) => {
+        assert_signer($account)?;
+        account_constraints!($account; $($($rest)*)?);
+    };
+    ($account:ident; mut $(, $($rest:tt)*)?) => {
+        assert_writable($account)?;
+        account_constraints!($account; $($($rest)*)?);
+    };
+    ($account:ident; address = $address:expr => $error:ident $(, $($rest:tt)*)?) => {
+        assert_address($account, &$address, McPayError::$error)?;
+        account_constraints!($account; $($($rest)*)?);
+    };
+    ($account:ident; owner = $owner:expr => $error:ident $(, $($rest:tt)*)?) => {
+        assert_owner($account, &$owner, McPayError::$error)?;
+        account_constraints!($account; $($($rest)*)?);
+    };
+}
+
+macro_rules! account_is_signer {
+    () => { false };
+    (signer $(, $($rest:tt)*)?) => { true };
+    (mut $(, $($rest:tt)*)?) => { account_is_signer!($($($rest)*)?) };
+    (address = $address:expr => $error:ident $(, $($rest:tt)*)?) => { account_is_signer!($($($rest)*)?) };
+    (owner = $owner:expr => $error:ident $(, $($rest:tt)*)?) => { account_is_signer!($($($rest)*)?) };
+}
+
+macro_rules! account_is_writable {
+    () => { false };
+    (mut $(, $($rest:tt)*)?) => { true };
+    (signer $(, $($rest:tt)*)?) => { account_is_writable!($($($rest)*)?) };
+    (address = $address:expr => $error:ident $(, $($rest:tt)*)?) => { account_is_writable!($($($rest)*)?) };
+    (owner = $owner:expr => $error:ident $(, $($rest:tt)*)?) => { account_is_writable!($($($rest)*)?) };
+}
+
+// declares, for each instruction, the accounts struct the processor validates with
+// and the keys struct clients build the matching account metas from
+macro_rules! instruction_accounts {
+    ($(
+        $accounts:ident, $keys:ident {
+            $( $field:ident: [$($constraint:tt)*], )*
+        }
+    )*) => {$(
+        pub struct $accounts<'b, 'a> {
+            $( pub $field: &'b AccountInfo<'a>, )*
+            pub remaining_accounts: &'b [AccountInfo<'a>],
+        }
+
+        impl<'b, 'a> TryFrom<&'b [AccountInfo<'a>]> for $accounts<'b, 'a> {
+            type Error = ProgramError;
+
+            fn try_from(accounts: &'b [AccountInfo<'a>]) -> Result<Self, Self::Error> {
+                let accounts_iter = &mut accounts.iter();
+                $(
+                    let $field = next_account_info(accounts_iter)?;
+                    account_constraints!($field; $($constraint)*);
+                )*
+
+                Ok(Self {
+                    $( $field, )*
+                    remaining_accounts: accounts_iter.as_slice(),
+                })
+            }
+        }
+
+        pub struct $keys {
+            $( pub $field: Pubkey, )*
+        }
+
+        impl $keys {
+            pub fn to_account_metas(&self, remaining_accounts: &[AccountMeta]) -> Vec<AccountMeta> {
+                let mut account_metas = vec![
+                    $(
+                        AccountMeta {
+                            pubkey: self.$field,
+                            is_signer: account_is_signer!($($constraint)*),
+                            is_writable: account_is_writable!($($constraint)*),
+                        },
+                    )*
+                ];
+                account_metas.extend_from_slice(remaining_accounts);
+
+                account_metas
+            }
+        }
+    )*};
+}
+
+instruction_accounts! {
+    // proofs follow, then the leaf owner when the signer is the leaf delegate
+    ClockInAccounts, ClockInKeys {
+        signer: [signer, mut], // 0
+        program_state_pda: [mut], // 1
+        asset_state_pda: [mut], // 2
+        leaf_delegate: [], // 3
+        merkle_tree: [], // 4
+        spl_account_compression_program_id: [], // 5
+        system_program_id: [address = solana_program::system_program::id() => InvalidSystemProgramID], // 6
+        wallet_state_pda: [mut], // 7
+        referrer_wallet_state_pda: [], // 8
+        mcpay_vault_pda: [mut], // 9
+        mcdegens_treasury: [mut], // 10
+        asset_flag_pda: [], // 11
+        rent_payer: [signer, mut], // 12
+    }
+
+    // proofs follow, then the leaf owner when the signer is the leaf delegate
+    ClockOutAccounts, ClockOutKeys {
+        signer: [signer, mut], // 0
+        program_state_pda: [mut], // 1
+        asset_state_pda: [mut], // 2
+        leaf_delegate: [], // 3
+        merkle_tree: [], // 4
+        spl_account_compression_program_id: [], // 5
+        spl_token_program_id: [address = spl_token::id() => InvalidSPLTokenProgramID], // 6
+        mcpay_vault_pda: [], // 7
+        mcpay_vault_pickle_ata: [mut, owner = spl_token::id() => InvalidTokenAccountOwner], // 8
+        payout_pickle_ata: [mut], // 9
+        clock_in_wallet: [], // 10
+        wallet_state_pda: [mut], // 11
+        season_pda: [mut], // 12
+        season_record_pda: [mut], // 13
+        system_program_id: [address = solana_program::system_program::id() => InvalidSystemProgramID], // 14
+        referrer_wallet_state_pda: [mut], // 15
+        pickle_mint: [mut], // 16
+        vesting_pda: [mut], // 17
+        asset_flag_pda: [], // 18
+        payout_wallet: [], // 19
+        spl_associated_token_program_id: [address = spl_associated_token_account::id() => InvalidSPLAssociatedTokenProgramID], // 20
+        rent_payer: [mut], // 21
+    }
+
+    UpdateStateAccounts, UpdateStateKeys {
+        signer: [signer, mut], // 0
+        program_state_pda: [mut], // 1
+        system_program_id: [address = solana_program::system_program::id() => InvalidSystemProgramID], // 2
+    }
+
+    CloseProgramStateAccounts, CloseProgramStateKeys {
+        signer: [signer, mut], // 0
+        program_state_pda: [mut, owner = crate::id() => InvalidAccountOwner], // 1
+    }
+
+    // the treasury pda follows for treasury types above 0
+    TransferPickleAccounts, TransferPickleKeys {
+        signer: [signer], // 0
+        program_state_pda: [], // 1
+        mcpay_vault_pda: [], // 2
+        mcpay_vault_pickle_ata: [mut, owner = spl_token::id() => InvalidTokenAccountOwner], // 3
+        treasury_pickle_ata: [mut], // 4
+        spl_token_program_id: [address = spl_token::id() => InvalidSPLTokenProgramID], // 5
+    }
+
+    TransferSOLAccounts, TransferSOLKeys {
+        signer: [signer], // 0
+        program_state_pda: [], // 1
+        mcpay_vault_pda: [mut], // 2
+        mcdegens_treasury: [mut], // 3
+        system_program_id: [address = solana_program::system_program::id() => InvalidSystemProgramID], // 4
+    }
+
+    UpdateBonusBracketsAccounts, UpdateBonusBracketsKeys {
+        signer: [signer], // 0
+        program_state_pda: [mut], // 1
+    }
+
+    UpdateEmissionScheduleAccounts, UpdateEmissionScheduleKeys {
+        signer: [signer], // 0
+        program_state_pda: [mut], // 1
+    }
+
+    UpdateEmissionCapAccounts, UpdateEmissionCapKeys {
+        signer: [signer], // 0
+        program_state_pda: [mut], // 1
+    }
+
+    UpdateUtilizationAccounts, UpdateUtilizationKeys {
+        signer: [signer], // 0
+        program_state_pda: [mut], // 1
+    }
+
+    CreateSeasonAccounts, CreateSeasonKeys {
+        signer: [signer, mut], // 0
+        program_state_pda: [mut], // 1
+        season_pda: [mut], // 2
+        system_program_id: [address = solana_program::system_program::id() => InvalidSystemProgramID], // 3
+        mcpay_vault_pickle_ata: [owner = spl_token::id() => InvalidTokenAccountOwner], // 4
+        current_season_pda: [], // 5
+    }
+
+    DistributeSeasonBonusAccounts, DistributeSeasonBonusKeys {
+        program_state_pda: [mut], // 0
+        season_pda: [mut], // 1
+        season_record_pda: [mut], // 2
+        wallet: [mut], // 3
+        wallet_pickle_ata: [mut], // 4
+        spl_token_program_id: [address = spl_token::id() => InvalidSPLTokenProgramID], // 5
+        mcpay_vault_pda: [], // 6
+        mcpay_vault_pickle_ata: [mut, owner = spl_token::id() => InvalidTokenAccountOwner], // 7
+    }
+
+    UpdateReferralAccounts, UpdateReferralKeys {
+        signer: [signer], // 0
+        program_state_pda: [mut], // 1
+    }
+
+    ClaimReferralRewardsAccounts, ClaimReferralRewardsKeys {
+        signer: [signer], // 0
+        program_state_pda: [mut], // 1
+        wallet_state_pda: [mut], // 2
+        signer_pickle_ata: [mut], // 3
+        spl_token_program_id: [address = spl_token::id() => InvalidSPLTokenProgramID], // 4
+        mcpay_vault_pda: [], // 5
+        mcpay_vault_pickle_ata: [mut, owner = spl_token::id() => InvalidTokenAccountOwner], // 6
+    }
+
+    UpdateClockInFeesAccounts, UpdateClockInFeesKeys {
+        signer: [signer], // 0
+        program_state_pda: [mut], // 1
+    }
+
+    AddTreasuryAccounts, AddTreasuryKeys {
+        signer: [signer, mut], // 0
+        program_state_pda: [], // 1
+        treasury_pda: [mut], // 2
+        treasury_pickle_ata: [], // 3
+        system_program_id: [address = solana_program::system_program::id() => InvalidSystemProgramID], // 4
+    }
+
+    RemoveTreasuryAccounts, RemoveTreasuryKeys {
+        signer: [signer, mut], // 0
+        treasury_pda: [mut, owner = crate::id() => InvalidAccountOwner], // 1
+    }
+
+    UpdateBurnAccounts, UpdateBurnKeys {
+        signer: [signer], // 0
+        program_state_pda: [mut], // 1
+    }
+
+    UpdateVestingAccounts, UpdateVestingKeys {
+        signer: [signer], // 0
+        program_state_pda: [mut], // 1
+    }
+
+    ClaimVestedAccounts, ClaimVestedKeys {
+        signer: [signer, mut], // 0
+        program_state_pda: [mut], // 1
+        vesting_pda: [mut], // 2
+        signer_pickle_ata: [mut], // 3
+        spl_token_program_id: [address = spl_token::id() => InvalidSPLTokenProgramID], // 4
+        mcpay_vault_pda: [], // 5
+        mcpay_vault_pickle_ata: [mut, owner = spl_token::id() => InvalidTokenAccountOwner], // 6
+    }
+
+    SetAssetFlagAccounts, SetAssetFlagKeys {
+        signer: [signer, mut], // 0
+        asset_flag_pda: [mut], // 1
+        system_program_id: [address = solana_program::system_program::id() => InvalidSystemProgramID], // 2
+    }
+
+    UpdateAllowlistAccounts, UpdateAllowlistKeys {
+        signer: [signer], // 0
+        program_state_pda: [mut], // 1
+    }
+
+    // the token accounts are only read when the chips due are paid out
+    ForceCloseAssetStateAccounts, ForceCloseAssetStateKeys {
+        signer: [signer], // 0
+        program_state_pda: [mut], // 1
+        asset_state_pda: [mut], // 2
+        clock_in_wallet: [], // 3
+        wallet_state_pda: [mut], // 4
+        spl_token_program_id: [address = spl_token::id() => InvalidSPLTokenProgramID], // 5
+        mcpay_vault_pda: [], // 6
+        mcpay_vault_pickle_ata: [mut, owner = spl_token::id() => InvalidTokenAccountOwner], // 7
+        clock_in_wallet_pickle_ata: [mut], // 8
+        rent_payer: [mut], // 9
+    }
+
+    UpdatePayoutPolicyAccounts, UpdatePayoutPolicyKeys {
+        signer: [signer], // 0
+        program_state_pda: [mut], // 1
+    }
+
+    // asset state, leaf delegate, asset flag and proofs follow for each entry,
+    // then the leaf owner when the signer is the leaf delegate
+    ClockInBatchAccounts, ClockInBatchKeys {
+        signer: [signer, mut], // 0
+        program_state_pda: [mut], // 1
+        merkle_tree: [], // 2
+        spl_account_compression_program_id: [], // 3
+        system_program_id: [address = solana_program::system_program::id() => InvalidSystemProgramID], // 4
+        wallet_state_pda: [mut], // 5
+        referrer_wallet_state_pda: [], // 6
+        mcpay_vault_pda: [mut], // 7
+        mcdegens_treasury: [mut], // 8
+        rent_payer: [signer, mut], // 9
+    }
+
+    // asset state, leaf delegate, asset flag and proofs follow for each entry,
+    // then the leaf owner when the signer is the leaf delegate
+    ClockOutBatchAccounts, ClockOutBatchKeys {
+        signer: [signer, mut], // 0
+        program_state_pda: [mut], // 1
+        merkle_tree: [], // 2
+        spl_account_compression_program_id: [], // 3
+        spl_token_program_id: [address = spl_token::id() => InvalidSPLTokenProgramID], // 4
+        mcpay_vault_pda: [], // 5
+        mcpay_vault_pickle_ata: [mut, owner = spl_token::id() => InvalidTokenAccountOwner], // 6
+        payout_pickle_ata: [mut], // 7
+        clock_in_wallet: [], // 8
+        wallet_state_pda: [mut], // 9
+        season_pda: [mut], // 10
+        season_record_pda: [mut], // 11
+        system_program_id: [address = solana_program::system_program::id() => InvalidSystemProgramID], // 12
+        referrer_wallet_state_pda: [mut], // 13
+        pickle_mint: [mut], // 14
+        vesting_pda: [mut], // 15
+        payout_wallet: [], // 16
+        spl_associated_token_program_id: [address = spl_associated_token_account::id() => InvalidSPLAssociatedTokenProgramID], // 17
+        rent_payer: [mut], // 18
+    }
+
+    UpdateCoreCollectionAccounts, UpdateCoreCollectionKeys {
+        signer: [signer], // 0
+        program_state_pda: [mut], // 1
+    }
+
+    ClockInCoreAccounts, ClockInCoreKeys {
+        signer: [signer, mut], // 0
+        program_state_pda: [mut], // 1
+        asset_state_pda: [mut], // 2
+        asset: [mut, owner = mpl_core::ID => InvalidCoreAsset], // 3
+        collection: [mut], // 4
+        mpl_core_program_id: [address = mpl_core::ID => InvalidMplCoreProgramID], // 5
+        system_program_id: [address = solana_program::system_program::id() => InvalidSystemProgramID], // 6
+        wallet_state_pda: [mut], // 7
+        referrer_wallet_state_pda: [], // 8
+        mcpay_vault_pda: [mut], // 9
+        mcdegens_treasury: [mut], // 10
+        asset_flag_pda: [], // 11
+        rent_payer: [signer, mut], // 12
+    }
+
+    ClockOutCoreAccounts, ClockOutCoreKeys {
+        signer: [signer, mut], // 0
+        program_state_pda: [mut], // 1
+        asset_state_pda: [mut], // 2
+        asset: [mut, owner = mpl_core::ID => InvalidCoreAsset], // 3
+        collection: [mut], // 4
+        mpl_core_program_id: [address = mpl_core::ID => InvalidMplCoreProgramID], // 5
+        spl_token_program_id: [address = spl_token::id() => InvalidSPLTokenProgramID], // 6
+        mcpay_vault_pda: [], // 7
+        mcpay_vault_pickle_ata: [mut, owner = spl_token::id() => InvalidTokenAccountOwner], // 8
+        payout_pickle_ata: [mut], // 9
+        clock_in_wallet: [], // 10
+        wallet_state_pda: [mut], // 11
+        season_pda: [mut], // 12
+        season_record_pda: [mut], // 13
+        system_program_id: [address = solana_program::system_program::id() => InvalidSystemProgramID], // 14
+        referrer_wallet_state_pda: [mut], // 15
+        pickle_mint: [mut], // 16
+        vesting_pda: [mut], // 17
+        asset_flag_pda: [], // 18
+        payout_wallet: [], // 19
+        spl_associated_token_program_id: [address = spl_associated_token_account::id() => InvalidSPLAssociatedTokenProgramID], // 20
+        rent_payer: [mut], // 21
+    }
+
+    UpdateLegacyCollectionAccounts, UpdateLegacyCollectionKeys {
+        signer: [signer], // 0
+        program_state_pda: [mut], // 1
+    }
+
+    ClockInLegacyAccounts, ClockInLegacyKeys {
+        signer: [signer, mut], // 0
+        program_state_pda: [mut], // 1
+        asset_state_pda: [mut], // 2
+        mint: [], // 3
+        token_account: [mut, owner = spl_token::id() => InvalidTokenAccount], // 4
+        metadata_pda: [], // 5
+        edition_pda: [], // 6
+        token_metadata_program_id: [address = mpl_token_metadata::ID => InvalidTokenMetadataProgramID], // 7
+        spl_token_program_id: [address = spl_token::id() => InvalidSPLTokenProgramID], // 8
+        system_program_id: [address = solana_program::system_program::id() => InvalidSystemProgramID], // 9
+        wallet_state_pda: [mut], // 10
+        referrer_wallet_state_pda: [], // 11
+        mcpay_vault_pda: [mut], // 12
+        mcdegens_treasury: [mut], // 13
+        asset_flag_pda: [], // 14
+        rent_payer: [signer, mut], // 15
+    }
+
+    ClockOutLegacyAccounts, ClockOutLegacyKeys {
+        signer: [signer, mut], // 0
+        program_state_pda: [mut], // 1
+        asset_state_pda: [mut], // 2
+        mint: [], // 3
+        token_account: [mut, owner = spl_token::id() => InvalidTokenAccount], // 4
+        metadata_pda: [], // 5
+        edition_pda: [], // 6
+        token_metadata_program_id: [address = mpl_token_metadata::ID => InvalidTokenMetadataProgramID], // 7
+        spl_token_program_id: [address = spl_token::id() => InvalidSPLTokenProgramID], // 8
+        mcpay_vault_pda: [mut], // 9
+        mcpay_vault_pickle_ata: [mut, owner = spl_token::id() => InvalidTokenAccountOwner], // 10
+        payout_pickle_ata: [mut], // 11
+        clock_in_wallet: [], // 12
+        wallet_state_pda: [mut], // 13
+        season_pda: [mut], // 14
+        season_record_pda: [mut], // 15
+        system_program_id: [address = solana_program::system_program::id() => InvalidSystemProgramID], // 16
+        referrer_wallet_state_pda: [mut], // 17
+        pickle_mint: [mut], // 18
+        vesting_pda: [mut], // 19
+        asset_flag_pda: [], // 20
+        payout_wallet: [], // 21
+        spl_associated_token_program_id: [address = spl_associated_token_account::id() => InvalidSPLAssociatedTokenProgramID], // 22
+        rent_payer: [mut], // 23
+    }
+
+    UpdateMerkleTreeV2Accounts, UpdateMerkleTreeV2Keys {
+        signer: [signer], // 0
+        program_state_pda: [mut], // 1
+    }
+
+    MigrateStateAccounts, MigrateStateKeys {
+        signer: [signer, mut], // 0
+        state_account: [mut], // 1
+        system_program_id: [address = solana_program::system_program::id() => InvalidSystemProgramID], // 2
+    }
+
+    UpdateAssetsClockedInAccounts, UpdateAssetsClockedInKeys {
+        signer: [signer], // 0
+        program_state_pda: [mut], // 1
+    }
+
+    CloseSeasonAccounts, CloseSeasonKeys {
+        signer: [signer, mut], // 0
+        program_state_pda: [mut], // 1
+        season_pda: [mut, owner = crate::id() => InvalidAccountOwner], // 2
+    }
+}
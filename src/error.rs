@@ -53,6 +53,124 @@ pub enum McPayError {
     NoUpdatesIndicated,
     #[error("CERROR: AmountOverflow")]
     AmountOverflow,
+    #[error("CERROR: Invalid Wallet State PDA")]
+    InvalidWalletStatePDA,
+    #[error("CERROR: Invalid Bonus Bracket")]
+    InvalidBonusBracket,
+    #[error("CERROR: Invalid Emission Schedule")]
+    InvalidEmissionSchedule,
+    #[error("CERROR: Invalid Emission Cap")]
+    InvalidEmissionCap,
+    #[error("CERROR: Emission Cap Reached")]
+    EmissionCapReached,
+    #[error("CERROR: Invalid Utilization")]
+    InvalidUtilization,
+    #[error("CERROR: Invalid Season PDA")]
+    InvalidSeasonPDA,
+    #[error("CERROR: Invalid Season Record PDA")]
+    InvalidSeasonRecordPDA,
+    #[error("CERROR: Invalid Season")]
+    InvalidSeason,
+    #[error("CERROR: Season Already Exists")]
+    SeasonAlreadyExists,
+    #[error("CERROR: Season Not Ended")]
+    SeasonNotEnded,
+    #[error("CERROR: No Season Points")]
+    NoSeasonPoints,
+    #[error("CERROR: Invalid Referral")]
+    InvalidReferral,
+    #[error("CERROR: Invalid Referrer")]
+    InvalidReferrer,
+    #[error("CERROR: No Referral Rewards")]
+    NoReferralRewards,
+    #[error("CERROR: Invalid Clock In Fees")]
+    InvalidClockInFees,
+    #[error("CERROR: Invalid Treasury PDA")]
+    InvalidTreasuryPDA,
+    #[error("CERROR: Invalid Treasury Type")]
+    InvalidTreasuryType,
+    #[error("CERROR: Treasury Not Found")]
+    TreasuryNotFound,
+    #[error("CERROR: Invalid Treasury Pickle ATA")]
+    InvalidTreasuryPickleATA,
+    #[error("CERROR: Invalid Burn")]
+    InvalidBurn,
+    #[error("CERROR: Invalid Pickle Mint")]
+    InvalidPickleMint,
+    #[error("CERROR: Invalid Vesting")]
+    InvalidVesting,
+    #[error("CERROR: Invalid Vesting PDA")]
+    InvalidVestingPDA,
+    #[error("CERROR: Nothing Vested")]
+    NothingVested,
+    #[error("CERROR: Invalid Asset Flag PDA")]
+    InvalidAssetFlagPDA,
+    #[error("CERROR: Invalid Asset Flag")]
+    InvalidAssetFlag,
+    #[error("CERROR: Asset Denied")]
+    AssetDenied,
+    #[error("CERROR: Asset Not Allowed")]
+    AssetNotAllowed,
+    #[error("CERROR: Invalid Payout Policy")]
+    InvalidPayoutPolicy,
+    #[error("CERROR: Invalid Payout Wallet")]
+    InvalidPayoutWallet,
+    #[error("CERROR: Invalid SPL Associated Token Program ID")]
+    InvalidSPLAssociatedTokenProgramID,
+    #[error("CERROR: Invalid Rent Payer")]
+    InvalidRentPayer,
+    #[error("CERROR: Invalid Leaf Owner")]
+    InvalidLeafOwner,
+    #[error("CERROR: Invalid Batch")]
+    InvalidBatch,
+    #[error("CERROR: Invalid Merkle Tree Header")]
+    InvalidMerkleTreeHeader,
+    #[error("CERROR: Invalid Merkle Tree Canopy")]
+    InvalidCanopy,
+    #[error("CERROR: Invalid Proof Length")]
+    InvalidProofLength,
+    #[error("CERROR: Invalid MPL Core Program ID")]
+    InvalidMplCoreProgramID,
+    #[error("CERROR: Invalid Core Asset")]
+    InvalidCoreAsset,
+    #[error("CERROR: Invalid Core Collection")]
+    InvalidCoreCollection,
+    #[error("CERROR: Invalid Asset Owner")]
+    InvalidAssetOwner,
+    #[error("CERROR: Invalid Token Metadata Program ID")]
+    InvalidTokenMetadataProgramID,
+    #[error("CERROR: Invalid Metadata PDA")]
+    InvalidMetadataPDA,
+    #[error("CERROR: Invalid Edition PDA")]
+    InvalidEditionPDA,
+    #[error("CERROR: Invalid Legacy Collection")]
+    InvalidLegacyCollection,
+    #[error("CERROR: Invalid Token Account")]
+    InvalidTokenAccount,
+    #[error("CERROR: Invalid Leaf Version")]
+    InvalidLeafVersion,
+    #[error("CERROR: Invalid Account Discriminator")]
+    InvalidAccountDiscriminator,
+    #[error("CERROR: Invalid Account Version")]
+    InvalidAccountVersion,
+    #[error("CERROR: Invalid Account Type")]
+    InvalidAccountType,
+    #[error("CERROR: Account Not Signer")]
+    AccountNotSigner,
+    #[error("CERROR: Account Not Writable")]
+    AccountNotWritable,
+    #[error("CERROR: Invalid Account Owner")]
+    InvalidAccountOwner,
+    #[error("CERROR: Invalid Token Account Owner")]
+    InvalidTokenAccountOwner,
+    #[error("CERROR: Asset Frozen")]
+    AssetFrozen,
+    #[error("CERROR: Invalid Leaf Index")]
+    InvalidLeafIndex,
+    #[error("CERROR: Season Active")]
+    SeasonActive,
+    #[error("CERROR: Invalid Allowlist")]
+    InvalidAllowlist,
 }
 
 impl From<McPayError> for ProgramError {
@@ -6,11 +6,37 @@ use solana_program::program_error::ProgramError;
 
 use crate::error::McPayError;
 use crate::state::{
+    AddTreasuryData,
+    ClockInBatchData,
+    ClockInCoreData,
     ClockInData, 
+    ClockInLegacyData,
+    ClockOutBatchData,
     ClockOutData,
+    CloseSeasonData,
+    CreateSeasonData,
+    DistributeSeasonBonusData,
+    ForceCloseAssetStateData,
+    MigrateStateData,
+    RemoveTreasuryData,
+    SetAssetFlagData,
     TransferPickleData,
     TransferSOLData,
+    UpdateAllowlistData,
+    UpdateAssetsClockedInData,
+    UpdateBonusBracketsData,
+    UpdateBurnData,
+    UpdateClockInFeesData,
+    UpdateCoreCollectionData,
+    UpdateEmissionCapData,
+    UpdateEmissionScheduleData,
+    UpdateLegacyCollectionData,
+    UpdateMerkleTreeV2Data,
+    UpdatePayoutPolicyData,
+    UpdateReferralData,
     UpdateStateData,
+    UpdateUtilizationData,
+    UpdateVestingData,
 };
 
 #[derive(BorshDeserialize, BorshSerialize, Debug, Clone)]
@@ -30,7 +56,96 @@ pub enum McPayInstruction {
     },
     UpdateState {
         update_state_data: UpdateStateData,
-    }
+    },
+    UpdateBonusBrackets {
+        update_bonus_brackets_data: UpdateBonusBracketsData,
+    },
+    UpdateEmissionSchedule {
+        update_emission_schedule_data: UpdateEmissionScheduleData,
+    },
+    UpdateEmissionCap {
+        update_emission_cap_data: UpdateEmissionCapData,
+    },
+    UpdateUtilization {
+        update_utilization_data: UpdateUtilizationData,
+    },
+    CreateSeason {
+        create_season_data: CreateSeasonData,
+    },
+    DistributeSeasonBonus {
+        distribute_season_bonus_data: DistributeSeasonBonusData,
+    },
+    UpdateReferral {
+        update_referral_data: UpdateReferralData,
+    },
+    ClaimReferralRewards{},
+    UpdateClockInFees {
+        update_clock_in_fees_data: UpdateClockInFeesData,
+    },
+    AddTreasury {
+        add_treasury_data: AddTreasuryData,
+    },
+    RemoveTreasury {
+        remove_treasury_data: RemoveTreasuryData,
+    },
+    UpdateBurn {
+        update_burn_data: UpdateBurnData,
+    },
+    UpdateVesting {
+        update_vesting_data: UpdateVestingData,
+    },
+    ClaimVested{},
+    SetAssetFlag {
+        set_asset_flag_data: SetAssetFlagData,
+    },
+    UpdateAllowlist {
+        update_allowlist_data: UpdateAllowlistData,
+    },
+    ForceCloseAssetState {
+        force_close_asset_state_data: ForceCloseAssetStateData,
+    },
+    UpdatePayoutPolicy {
+        update_payout_policy_data: UpdatePayoutPolicyData,
+    },
+    // every entry is a compressed asset of the one leaf owner in the same merkle tree, sharing the wallet state,
+    // rent payer and fee accounts; after the fixed accounts each entry takes its asset state, leaf delegate,
+    // asset flag and then proof_length proof accounts, in entry order, and the leaf owner comes last, only
+    // when the signer is the leaf delegate
+    ClockInBatch {
+        clock_in_batch_data: ClockInBatchData,
+    },
+    // every entry is a compressed asset of the one leaf owner in the same merkle tree, clocked in by the same
+    // clock in wallet and rent payer and paid to the same payout wallet; the trailing accounts are laid out
+    // as for ClockInBatch
+    ClockOutBatch {
+        clock_out_batch_data: ClockOutBatchData,
+    },
+    UpdateCoreCollection {
+        update_core_collection_data: UpdateCoreCollectionData,
+    },
+    ClockInCore {
+        clock_in_core_data: ClockInCoreData,
+    },
+    ClockOutCore{},
+    UpdateLegacyCollection {
+        update_legacy_collection_data: UpdateLegacyCollectionData,
+    },
+    ClockInLegacy {
+        clock_in_legacy_data: ClockInLegacyData,
+    },
+    ClockOutLegacy{},
+    UpdateMerkleTreeV2 {
+        update_merkle_tree_v2_data: UpdateMerkleTreeV2Data,
+    },
+    MigrateState {
+        migrate_state_data: MigrateStateData,
+    },
+    UpdateAssetsClockedIn {
+        update_assets_clocked_in_data: UpdateAssetsClockedInData,
+    },
+    CloseSeason {
+        close_season_data: CloseSeasonData,
+    },
 }
 
 impl McPayInstruction {
@@ -40,20 +155,102 @@ impl McPayInstruction {
             .ok_or(McPayError::InvalidInstruction)?;
         Ok(match tag {
             0 => Self::ClockIn {
-                clock_in_data: ClockInData::try_from_slice(rest).unwrap()
+                clock_in_data: ClockInData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
             },
             1 => Self::ClockOut {
-                clock_out_data: ClockOutData::try_from_slice(rest).unwrap()
+                clock_out_data: ClockOutData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
             },
             2 => Self::UpdateState {
-                update_state_data: UpdateStateData::try_from_slice(rest).unwrap()
+                update_state_data: UpdateStateData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
             },
             3 => Self::CloseProgramState {},
             4 => Self::TransferPickle {
-                transfer_pickle_data: TransferPickleData::try_from_slice(rest).unwrap()
+                transfer_pickle_data: TransferPickleData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
             },
             5 => Self::TransferSOL {
-                transfer_sol_data: TransferSOLData::try_from_slice(rest).unwrap()
+                transfer_sol_data: TransferSOLData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            6 => Self::UpdateBonusBrackets {
+                update_bonus_brackets_data: UpdateBonusBracketsData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            7 => Self::UpdateEmissionSchedule {
+                update_emission_schedule_data: UpdateEmissionScheduleData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            8 => Self::UpdateEmissionCap {
+                update_emission_cap_data: UpdateEmissionCapData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            9 => Self::UpdateUtilization {
+                update_utilization_data: UpdateUtilizationData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            10 => Self::CreateSeason {
+                create_season_data: CreateSeasonData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            11 => Self::DistributeSeasonBonus {
+                distribute_season_bonus_data: DistributeSeasonBonusData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            12 => Self::UpdateReferral {
+                update_referral_data: UpdateReferralData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            13 => Self::ClaimReferralRewards {},
+            14 => Self::UpdateClockInFees {
+                update_clock_in_fees_data: UpdateClockInFeesData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            15 => Self::AddTreasury {
+                add_treasury_data: AddTreasuryData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            16 => Self::RemoveTreasury {
+                remove_treasury_data: RemoveTreasuryData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            17 => Self::UpdateBurn {
+                update_burn_data: UpdateBurnData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            18 => Self::UpdateVesting {
+                update_vesting_data: UpdateVestingData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            19 => Self::ClaimVested {},
+            20 => Self::SetAssetFlag {
+                set_asset_flag_data: SetAssetFlagData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            21 => Self::UpdateAllowlist {
+                update_allowlist_data: UpdateAllowlistData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            22 => Self::ForceCloseAssetState {
+                force_close_asset_state_data: ForceCloseAssetStateData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            23 => Self::UpdatePayoutPolicy {
+                update_payout_policy_data: UpdatePayoutPolicyData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            24 => Self::ClockInBatch {
+                clock_in_batch_data: ClockInBatchData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            25 => Self::ClockOutBatch {
+                clock_out_batch_data: ClockOutBatchData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            26 => Self::UpdateCoreCollection {
+                update_core_collection_data: UpdateCoreCollectionData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            27 => Self::ClockInCore {
+                clock_in_core_data: ClockInCoreData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            28 => Self::ClockOutCore {},
+            29 => Self::UpdateLegacyCollection {
+                update_legacy_collection_data: UpdateLegacyCollectionData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            30 => Self::ClockInLegacy {
+                clock_in_legacy_data: ClockInLegacyData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            31 => Self::ClockOutLegacy {},
+            32 => Self::UpdateMerkleTreeV2 {
+                update_merkle_tree_v2_data: UpdateMerkleTreeV2Data::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            33 => Self::MigrateState {
+                migrate_state_data: MigrateStateData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            34 => Self::UpdateAssetsClockedIn {
+                update_assets_clocked_in_data: UpdateAssetsClockedInData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
+            },
+            35 => Self::CloseSeason {
+                close_season_data: CloseSeasonData::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?
             },
             _ => return Err(McPayError::InvalidInstruction.into()),
         })
@@ -1,8 +1,12 @@
+pub mod accounts;
 pub mod entrypoint;
 pub mod instruction;
 pub mod error;
 pub mod processor;
 pub mod state;
+pub mod validation;
+
+solana_program::declare_id!("BtFLxrtCs4BR43jpHvZ9o4t3xk9zkKXx4JTuDhFTeD8W");
 
 #[cfg(not(feature = "no-entrypoint"))]
 use include_idl::include_idl;
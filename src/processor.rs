@@ -1,7 +1,3 @@
-use borsh::{
-    BorshDeserialize, 
-    BorshSerialize,
-};
 use solana_program::{
     account_info::{
         AccountInfo,
@@ -9,32 +5,138 @@ use solana_program::{
     }, 
     clock::Clock, 
     entrypoint::ProgramResult, 
+    instruction::{
+        AccountMeta,
+        Instruction,
+    },
     msg, 
-    program::invoke_signed, 
+    program::{
+        invoke,
+        invoke_signed,
+    }, 
     program_error::ProgramError, 
-    program_pack::Pack, 
     pubkey::Pubkey, 
     rent::Rent, 
     system_instruction, 
     sysvar::Sysvar,
 };
-use spl_associated_token_account::get_associated_token_address;
-use spl_token::state::Account;
-use std::str::FromStr;
+use spl_associated_token_account::{
+    get_associated_token_address,
+    instruction::create_associated_token_account_idempotent,
+};
 use utils::assert_true;
 
 use crate::{
+    accounts::{
+        AddTreasuryAccounts,
+        ClaimReferralRewardsAccounts,
+        ClaimVestedAccounts,
+        ClockInAccounts,
+        ClockInBatchAccounts,
+        ClockInCoreAccounts,
+        ClockInLegacyAccounts,
+        ClockOutAccounts,
+        ClockOutBatchAccounts,
+        ClockOutCoreAccounts,
+        ClockOutLegacyAccounts,
+        CloseProgramStateAccounts,
+        CloseSeasonAccounts,
+        CreateSeasonAccounts,
+        DistributeSeasonBonusAccounts,
+        ForceCloseAssetStateAccounts,
+        MigrateStateAccounts,
+        RemoveTreasuryAccounts,
+        SetAssetFlagAccounts,
+        TransferPickleAccounts,
+        TransferSOLAccounts,
+        UpdateAllowlistAccounts,
+        UpdateAssetsClockedInAccounts,
+        UpdateBonusBracketsAccounts,
+        UpdateBurnAccounts,
+        UpdateClockInFeesAccounts,
+        UpdateCoreCollectionAccounts,
+        UpdateEmissionCapAccounts,
+        UpdateEmissionScheduleAccounts,
+        UpdateLegacyCollectionAccounts,
+        UpdateMerkleTreeV2Accounts,
+        UpdatePayoutPolicyAccounts,
+        UpdateReferralAccounts,
+        UpdateStateAccounts,
+        UpdateUtilizationAccounts,
+        UpdateVestingAccounts,
+    },
     error::McPayError, 
     instruction::McPayInstruction, 
     state::{
+        ACCOUNT_HEADER_SIZE,
+        ACCOUNT_TYPE_ASSET_FLAG,
+        ACCOUNT_TYPE_ASSET_STATE,
+        ACCOUNT_TYPE_PROGRAM_STATE,
+        ACCOUNT_TYPE_SEASON_RECORD,
+        ACCOUNT_TYPE_SEASON_STATE,
+        ACCOUNT_TYPE_TREASURY_STATE,
+        ACCOUNT_TYPE_VESTING_STATE,
+        ACCOUNT_TYPE_WALLET_STATE,
+        ASSET_FLAG_ALLOW,
+        ASSET_FLAG_DENY,
+        ASSET_KIND_COMPRESSED,
+        ASSET_KIND_CORE,
+        ASSET_KIND_LEGACY,
+        AccountState,
+        AddTreasuryData,
+        AssetFlag,
         AssetState, 
+        BonusBracket,
+        ClockInBatchData,
+        ClockInCoreData,
         ClockInData, 
+        ClockInLegacyData,
+        ClockOutBatchData,
         ClockOutData,
+        CloseSeasonData,
+        CreateSeasonData,
+        DistributeSeasonBonusData,
+        ForceCloseAssetStateData,
+        LeafV2Data,
+        MigrateStateData,
+        MPL_ACCOUNT_COMPRESSION_ID,
+        PAYOUT_CLOCK_IN_WALLET,
+        PAYOUT_RECIPIENT,
         ProgramState,
+        RemoveTreasuryData,
+        SeasonRecord,
+        SeasonState,
+        SetAssetFlagData,
         TransferPickleData,
         TransferSOLData,
+        TreasuryState,
+        UpdateAllowlistData,
+        UpdateAssetsClockedInData,
+        UpdateBonusBracketsData,
+        UpdateBurnData,
+        UpdateClockInFeesData,
+        UpdateCoreCollectionData,
+        UpdateEmissionCapData,
+        UpdateEmissionScheduleData,
+        UpdateLegacyCollectionData,
+        UpdateMerkleTreeV2Data,
+        UpdatePayoutPolicyData,
+        UpdateReferralData,
         UpdateStateData,
-    }
+        UpdateUtilizationData,
+        UpdateVestingData,
+        VERIFY_LEAF_DISCRIMINATOR,
+        VestingState,
+        VestingTranche,
+        WalletState,
+    },
+    validation::{
+        assert_address,
+        assert_admin,
+        assert_owner,
+        assert_writable,
+        unpack_token_account,
+    },
 };
 
 pub struct Processor {}
@@ -103,6 +205,294 @@ impl Processor {
                     transfer_sol_data,
                 )
             },
+            McPayInstruction::UpdateBonusBrackets {
+                update_bonus_brackets_data
+            } => {
+                msg!("Update Bonus Brackets");
+                Self::process_update_bonus_brackets(
+                    program_id,
+                    accounts,
+                    update_bonus_brackets_data,
+                )
+            },
+            McPayInstruction::UpdateEmissionSchedule {
+                update_emission_schedule_data
+            } => {
+                msg!("Update Emission Schedule");
+                Self::process_update_emission_schedule(
+                    program_id,
+                    accounts,
+                    update_emission_schedule_data,
+                )
+            },
+            McPayInstruction::UpdateEmissionCap {
+                update_emission_cap_data
+            } => {
+                msg!("Update Emission Cap");
+                Self::process_update_emission_cap(
+                    program_id,
+                    accounts,
+                    update_emission_cap_data,
+                )
+            },
+            McPayInstruction::UpdateUtilization {
+                update_utilization_data
+            } => {
+                msg!("Update Utilization");
+                Self::process_update_utilization(
+                    program_id,
+                    accounts,
+                    update_utilization_data,
+                )
+            },
+            McPayInstruction::CreateSeason {
+                create_season_data
+            } => {
+                msg!("Create Season");
+                Self::process_create_season(
+                    program_id,
+                    accounts,
+                    create_season_data,
+                )
+            },
+            McPayInstruction::DistributeSeasonBonus {
+                distribute_season_bonus_data
+            } => {
+                msg!("Distribute Season Bonus");
+                Self::process_distribute_season_bonus(
+                    program_id,
+                    accounts,
+                    distribute_season_bonus_data,
+                )
+            },
+            McPayInstruction::UpdateReferral {
+                update_referral_data
+            } => {
+                msg!("Update Referral");
+                Self::process_update_referral(
+                    program_id,
+                    accounts,
+                    update_referral_data,
+                )
+            },
+            McPayInstruction::ClaimReferralRewards {} => {
+                msg!("Claim Referral Rewards");
+                Self::process_claim_referral_rewards(
+                    program_id,
+                    accounts,
+                )
+            },
+            McPayInstruction::UpdateClockInFees {
+                update_clock_in_fees_data
+            } => {
+                msg!("Update Clock In Fees");
+                Self::process_update_clock_in_fees(
+                    program_id,
+                    accounts,
+                    update_clock_in_fees_data,
+                )
+            },
+            McPayInstruction::AddTreasury {
+                add_treasury_data
+            } => {
+                msg!("Add Treasury");
+                Self::process_add_treasury(
+                    program_id,
+                    accounts,
+                    add_treasury_data,
+                )
+            },
+            McPayInstruction::RemoveTreasury {
+                remove_treasury_data
+            } => {
+                msg!("Remove Treasury");
+                Self::process_remove_treasury(
+                    program_id,
+                    accounts,
+                    remove_treasury_data,
+                )
+            },
+            McPayInstruction::UpdateBurn {
+                update_burn_data
+            } => {
+                msg!("Update Burn");
+                Self::process_update_burn(
+                    program_id,
+                    accounts,
+                    update_burn_data,
+                )
+            },
+            McPayInstruction::UpdateVesting {
+                update_vesting_data
+            } => {
+                msg!("Update Vesting");
+                Self::process_update_vesting(
+                    program_id,
+                    accounts,
+                    update_vesting_data,
+                )
+            },
+            McPayInstruction::ClaimVested {} => {
+                msg!("Claim Vested");
+                Self::process_claim_vested(
+                    program_id,
+                    accounts,
+                )
+            },
+            McPayInstruction::SetAssetFlag {
+                set_asset_flag_data
+            } => {
+                msg!("Set Asset Flag");
+                Self::process_set_asset_flag(
+                    program_id,
+                    accounts,
+                    set_asset_flag_data,
+                )
+            },
+            McPayInstruction::UpdateAllowlist {
+                update_allowlist_data
+            } => {
+                msg!("Update Allowlist");
+                Self::process_update_allowlist(
+                    program_id,
+                    accounts,
+                    update_allowlist_data,
+                )
+            },
+            McPayInstruction::ForceCloseAssetState {
+                force_close_asset_state_data
+            } => {
+                msg!("Force Close Asset State");
+                Self::process_force_close_asset_state(
+                    program_id,
+                    accounts,
+                    force_close_asset_state_data,
+                )
+            },
+            McPayInstruction::UpdatePayoutPolicy {
+                update_payout_policy_data
+            } => {
+                msg!("Update Payout Policy");
+                Self::process_update_payout_policy(
+                    program_id,
+                    accounts,
+                    update_payout_policy_data,
+                )
+            },
+            McPayInstruction::ClockInBatch {
+                clock_in_batch_data
+            } => {
+                msg!("Clock In Batch");
+                Self::process_clock_in_batch(
+                    program_id,
+                    accounts,
+                    clock_in_batch_data,
+                )
+            },
+            McPayInstruction::ClockOutBatch {
+                clock_out_batch_data
+            } => {
+                msg!("Clock Out Batch");
+                Self::process_clock_out_batch(
+                    program_id,
+                    accounts,
+                    clock_out_batch_data,
+                )
+            },
+            McPayInstruction::UpdateCoreCollection {
+                update_core_collection_data
+            } => {
+                msg!("Update Core Collection");
+                Self::process_update_core_collection(
+                    program_id,
+                    accounts,
+                    update_core_collection_data,
+                )
+            },
+            McPayInstruction::ClockInCore {
+                clock_in_core_data
+            } => {
+                msg!("Clock In Core");
+                Self::process_clock_in_core(
+                    program_id,
+                    accounts,
+                    clock_in_core_data,
+                )
+            },
+            McPayInstruction::ClockOutCore {} => {
+                msg!("Clock Out Core");
+                Self::process_clock_out_core(
+                    program_id,
+                    accounts,
+                )
+            },
+            McPayInstruction::UpdateLegacyCollection {
+                update_legacy_collection_data
+            } => {
+                msg!("Update Legacy Collection");
+                Self::process_update_legacy_collection(
+                    program_id,
+                    accounts,
+                    update_legacy_collection_data,
+                )
+            },
+            McPayInstruction::ClockInLegacy {
+                clock_in_legacy_data
+            } => {
+                msg!("Clock In Legacy");
+                Self::process_clock_in_legacy(
+                    program_id,
+                    accounts,
+                    clock_in_legacy_data,
+                )
+            },
+            McPayInstruction::ClockOutLegacy {} => {
+                msg!("Clock Out Legacy");
+                Self::process_clock_out_legacy(
+                    program_id,
+                    accounts,
+                )
+            },
+            McPayInstruction::UpdateMerkleTreeV2 {
+                update_merkle_tree_v2_data
+            } => {
+                msg!("Update Merkle Tree V2");
+                Self::process_update_merkle_tree_v2(
+                    program_id,
+                    accounts,
+                    update_merkle_tree_v2_data,
+                )
+            },
+            McPayInstruction::MigrateState {
+                migrate_state_data
+            } => {
+                msg!("Migrate State");
+                Self::process_migrate_state(
+                    program_id,
+                    accounts,
+                    migrate_state_data,
+                )
+            },
+            McPayInstruction::UpdateAssetsClockedIn {
+                update_assets_clocked_in_data
+            } => {
+                msg!("Update Assets Clocked In");
+                Self::process_update_assets_clocked_in(
+                    program_id,
+                    accounts,
+                    update_assets_clocked_in_data,
+                )
+            },
+            McPayInstruction::CloseSeason {
+                close_season_data
+            } => {
+                msg!("Close Season");
+                Self::process_close_season(
+                    program_id,
+                    accounts,
+                    close_season_data,
+                )
+            },
         }?;
 
         Ok(())
@@ -113,169 +503,237 @@ impl Processor {
         accounts: &[AccountInfo],
         clock_in_data: ClockInData,
     ) -> ProgramResult {
-        let accounts_iter = &mut accounts.iter();
-        let signer = next_account_info(accounts_iter)?; // 0
-        let program_state_pda = next_account_info(accounts_iter)?; // 1
-        let asset_state_pda = next_account_info(accounts_iter)?; // 2
-        let leaf_delegate = next_account_info(accounts_iter)?; // 3
-        let merkle_tree = next_account_info(accounts_iter)?; // 4
-        let spl_account_compression_program_id = next_account_info(accounts_iter)?; // 5
-        let system_program_id = next_account_info(accounts_iter)?; // 6
+        let ClockInAccounts {
+            signer,
+            program_state_pda,
+            asset_state_pda,
+            leaf_delegate,
+            merkle_tree,
+            spl_account_compression_program_id,
+            system_program_id,
+            wallet_state_pda,
+            referrer_wallet_state_pda,
+            mcpay_vault_pda,
+            mcdegens_treasury,
+            asset_flag_pda,
+            rent_payer,
+            remaining_accounts: trailing_accounts,
+        } = ClockInAccounts::try_from(accounts)?;
+        let accounts_iter = &mut trailing_accounts.iter();
 
         let mut remaining_accounts:  Vec<AccountInfo> = vec![];
         for _n in 0..clock_in_data.proof_length {
             let acct = next_account_info(accounts_iter)?;
             remaining_accounts.push(acct.clone());
         }
+        // without a trailing leaf owner the signer clocks in their own asset
+        let leaf_owner = next_account_info(accounts_iter).unwrap_or(signer);
 
-        if !signer.is_signer {
-            msg!("CERROR: Missing required signature");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        let (program_state, _program_state_bump) = Pubkey::find_program_address(
-            &[b"program-state"], 
+        let mut program_state_data = Self::clock_in_program_state(
             program_id,
-        );
-        assert_true(
-            program_state == *program_state_pda.key,
-            ProgramError::from(McPayError::InvalidProgramStatePDA),
-            "CERROR: Invalid program state pda",
+            program_state_pda,
+            mcpay_vault_pda,
+            mcdegens_treasury,
         )?;
 
-        let program_state_data: ProgramState = ProgramState::try_from_slice(&program_state_pda.data.borrow())?;
-        assert_true(
-            program_state_data.is_initialized,
-            ProgramError::from(McPayError::ProgramStateNotInitialized),
-            "CERROR: Program state not initialized",
+        Self::check_merkle_tree(
+            &program_state_data,
+            merkle_tree,
+            spl_account_compression_program_id,
         )?;
 
-        assert_true(
-            *merkle_tree.key == program_state_data.merkle_tree,
-            ProgramError::from(McPayError::InvalidMerkleTree),
-            "CERROR: Invalid merkle tree",
+        let mut wallet_state_data = Self::clock_in_wallet_state(
+            program_id,
+            leaf_owner.key,
+            rent_payer,
+            wallet_state_pda,
+            referrer_wallet_state_pda,
+            system_program_id,
+            &clock_in_data.referrer,
         )?;
 
-        assert_true(
-            program_state_data.clock_in_is_enabled == 1,
-            ProgramError::from(McPayError::ClockInDisabled),
-            "CERROR: Clock in disabled",
-        )?;
+        let clock = Clock::get()?;
+        let utime = clock.unix_timestamp;
 
         let asset_id = mpl_bubblegum::utils::get_asset_id(merkle_tree.key, clock_in_data.nonce);
         msg!("asset_id {}", asset_id);
 
-        let (asset_state, asset_state_bump) = Pubkey::find_program_address(
-            &[
-                b"asset-state",
-                asset_id.as_ref(),
-            ],
+        Self::verify_leaf(
+            signer,
+            leaf_owner,
+            leaf_delegate,
+            merkle_tree,
+            spl_account_compression_program_id,
+            &remaining_accounts,
+            asset_id,
+            clock_in_data.nonce,
+            &clock_in_data.root,
+            &clock_in_data.data_hash,
+            &clock_in_data.creator_hash,
+            &clock_in_data.leaf_v2,
+        )?;
+
+        let fee_lamports = Self::clock_in_asset(
             program_id,
-        );
-        assert_true(
-            asset_state == *asset_state_pda.key,
-            ProgramError::from(McPayError::InvalidAssetStatePDA),
-            "CERROR: Invalid asset state pda",
+            &mut program_state_data,
+            &mut wallet_state_data,
+            leaf_owner.key,
+            rent_payer,
+            system_program_id,
+            asset_state_pda,
+            asset_flag_pda,
+            asset_id,
+            ASSET_KIND_COMPRESSED,
+            clock_in_data.asset_state_bump,
+            clock_in_data.asset_flag_bump,
+            clock_in_data.level,
+            &clock_in_data.payout_recipient,
+            utime,
+        )?;
+
+        wallet_state_data.store(wallet_state_pda)?;
+        program_state_data.store(program_state_pda)?;
+
+        Self::collect_clock_in_fee(
+            &program_state_data,
+            fee_lamports,
+            rent_payer,
+            mcpay_vault_pda,
+            mcdegens_treasury,
+            system_program_id,
         )?;
 
+        Ok(())
+    }
+
+    fn process_clock_in_batch(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        clock_in_batch_data: ClockInBatchData,
+    ) -> ProgramResult {
+        let ClockInBatchAccounts {
+            signer,
+            program_state_pda,
+            merkle_tree,
+            spl_account_compression_program_id,
+            system_program_id,
+            wallet_state_pda,
+            referrer_wallet_state_pda,
+            mcpay_vault_pda,
+            mcdegens_treasury,
+            rent_payer,
+            remaining_accounts: trailing_accounts,
+        } = ClockInBatchAccounts::try_from(accounts)?;
+        let accounts_iter = &mut trailing_accounts.iter();
+
         assert_true(
-            *spl_account_compression_program_id.key == spl_account_compression::id(),
-            ProgramError::from(McPayError::InvalidSPLAccountCompressionProgramID),
-            "CERROR: Invalid SPL Account Compression Program ID",
+            !clock_in_batch_data.entries.is_empty(),
+            ProgramError::from(McPayError::InvalidBatch),
+            "CERROR: Empty batch",
         )?;
-        
+
+        // without a leaf owner after the entries the signer clocks in their own assets
+        let entry_accounts: usize = clock_in_batch_data.entries.iter()
+            .map(|clock_in_entry| 3 + clock_in_entry.proof_length as usize)
+            .sum();
         assert_true(
-            *system_program_id.key == solana_program::system_program::id(),
-            ProgramError::from(McPayError::InvalidSystemProgramID),
-            "CERROR: Invalid System Program ID",
+            trailing_accounts.len() == entry_accounts || trailing_accounts.len() == entry_accounts + 1,
+            ProgramError::from(McPayError::InvalidBatch),
+            "CERROR: Batch accounts do not match the entries' proof lengths",
         )?;
+        let leaf_owner = trailing_accounts.get(entry_accounts).unwrap_or(signer);
 
-        let leaf = mpl_bubblegum::types::LeafSchema::V1 { 
-            id: asset_id, 
-            owner: *signer.key, 
-            delegate: *leaf_delegate.key, 
-            nonce: clock_in_data.nonce, 
-            data_hash: clock_in_data.data_hash.to_bytes(), 
-            creator_hash: clock_in_data.creator_hash.to_bytes(),
-        };
+        let mut program_state_data = Self::clock_in_program_state(
+            program_id,
+            program_state_pda,
+            mcpay_vault_pda,
+            mcdegens_treasury,
+        )?;
 
-        let verify_leaf_cpi = mpl_bubblegum::instructions::VerifyLeafCpi::new(
-            spl_account_compression_program_id, 
-            mpl_bubblegum::instructions::VerifyLeafCpiAccounts {
-                merkle_tree,
-            }, 
-            mpl_bubblegum::instructions::VerifyLeafInstructionArgs {
-                index: clock_in_data.nonce.try_into().unwrap(),
-                leaf: leaf.hash(),
-                root: clock_in_data.root.to_bytes(),
-            }
-        );
-        verify_leaf_cpi.invoke_with_remaining_accounts(
-            remaining_accounts
-                .iter()
-                .map(|account| (account, false, false))
-                .collect::<Vec<_>>()
-                .as_slice()
+        Self::check_merkle_tree(
+            &program_state_data,
+            merkle_tree,
+            spl_account_compression_program_id,
         )?;
-        
+
+        let mut wallet_state_data = Self::clock_in_wallet_state(
+            program_id,
+            leaf_owner.key,
+            rent_payer,
+            wallet_state_pda,
+            referrer_wallet_state_pda,
+            system_program_id,
+            &clock_in_batch_data.referrer,
+        )?;
+
         let clock = Clock::get()?;
         let utime = clock.unix_timestamp;
 
-        let mut clock_out_utime = utime;        
-        let mut chips_due = 0;
-        if clock_in_data.level == 1 {
-            clock_out_utime += 86_400;
-            chips_due = program_state_data.level_one_rate;
-        } else if clock_in_data.level == 7 {
-            clock_out_utime += 86_400 * clock_in_data.level as i64;
-            chips_due = program_state_data.level_seven_rate;
-        } else if clock_in_data.level == 30 {
-            clock_out_utime += 86_400 * clock_in_data.level as i64;
-            chips_due = program_state_data.level_thirty_rate;
-        }         
-        assert_true(
-            chips_due > 0,
-            ProgramError::from(McPayError::InvalidLevel),
-            "CERROR: Invalid level",
-        )?;
+        let mut fee_lamports: u64 = 0;
+        for clock_in_entry in clock_in_batch_data.entries.iter() {
+            // asset state, leaf delegate and asset flag, then the entry's proof accounts
+            let asset_state_pda = next_account_info(accounts_iter)?;
+            let leaf_delegate = next_account_info(accounts_iter)?;
+            let asset_flag_pda = next_account_info(accounts_iter)?;
 
-        if asset_state_pda.data_is_empty() {
-            msg!("Creating Asset State");
-            let asset_state_size = 1 + 32 + 32 + 8 + 8 + 1 + 8;
-            invoke_signed(
-                &system_instruction::create_account(
-                    signer.key,
-                    &asset_state_pda.key,
-                    Rent::get()?.minimum_balance(asset_state_size),
-                    asset_state_size as u64,
-                    program_id,
-                ),
-                &[
-                    signer.clone(),
-                    asset_state_pda.clone(),
-                    system_program_id.clone(),
-                ],
-                &[&[
-                    b"asset-state",
-                    asset_id.as_ref(),
-                    &[asset_state_bump],
-                ]],
+            let mut remaining_accounts:  Vec<AccountInfo> = vec![];
+            for _n in 0..clock_in_entry.proof_length {
+                let acct = next_account_info(accounts_iter)?;
+                remaining_accounts.push(acct.clone());
+            }
+
+            let asset_id = mpl_bubblegum::utils::get_asset_id(merkle_tree.key, clock_in_entry.nonce);
+            msg!("asset_id {}", asset_id);
+
+            Self::verify_leaf(
+                signer,
+                leaf_owner,
+                leaf_delegate,
+                merkle_tree,
+                spl_account_compression_program_id,
+                &remaining_accounts,
+                asset_id,
+                clock_in_entry.nonce,
+                &clock_in_entry.root,
+                &clock_in_entry.data_hash,
+                &clock_in_entry.creator_hash,
+                &clock_in_entry.leaf_v2,
             )?;
 
-            let mut asset_state_data: AssetState = AssetState::try_from_slice(&asset_state_pda.data.borrow())?;
-            asset_state_data.is_initialized = true;
-            asset_state_data.clock_in_wallet = *signer.key;
-            asset_state_data.asset_id = asset_id;
-            asset_state_data.clock_in_utime = utime;
-            asset_state_data.clock_out_utime = clock_out_utime;
-            asset_state_data.level = clock_in_data.level;
-            asset_state_data.chips_due = chips_due;
-            asset_state_data.serialize(&mut &mut asset_state_pda.data.borrow_mut()[..])?;
-        } else {
-            msg!("CERROR: Asset already clocked in");
-            return Err(McPayError::AlreadyClockedIn.into());
+            let asset_fee_lamports = Self::clock_in_asset(
+                program_id,
+                &mut program_state_data,
+                &mut wallet_state_data,
+                leaf_owner.key,
+                rent_payer,
+                system_program_id,
+                asset_state_pda,
+                asset_flag_pda,
+                asset_id,
+                ASSET_KIND_COMPRESSED,
+                clock_in_entry.asset_state_bump,
+                clock_in_entry.asset_flag_bump,
+                clock_in_entry.level,
+                &clock_in_batch_data.payout_recipient,
+                utime,
+            )?;
+            fee_lamports = fee_lamports
+                .checked_add(asset_fee_lamports)
+                .ok_or(McPayError::AmountOverflow)?;
         }
 
+        wallet_state_data.store(wallet_state_pda)?;
+        program_state_data.store(program_state_pda)?;
+
+        Self::collect_clock_in_fee(
+            &program_state_data,
+            fee_lamports,
+            rent_payer,
+            mcpay_vault_pda,
+            mcdegens_treasury,
+            system_program_id,
+        )?;
+
         Ok(())
     }
 
@@ -284,213 +742,729 @@ impl Processor {
         accounts: &[AccountInfo],
         clock_out_data: ClockOutData,
     ) -> ProgramResult {
-        let accounts_iter = &mut accounts.iter();
-        let signer = next_account_info(accounts_iter)?; // 0
-        let program_state_pda = next_account_info(accounts_iter)?; // 1
-        let asset_state_pda = next_account_info(accounts_iter)?; // 2
-        let leaf_delegate = next_account_info(accounts_iter)?; // 3
-        let merkle_tree = next_account_info(accounts_iter)?; // 4
-        let spl_account_compression_program_id = next_account_info(accounts_iter)?; // 5
-        let spl_token_program_id = next_account_info(accounts_iter)?; // 6
-        let mcpay_vault_pda = next_account_info(accounts_iter)?; // 7
-        let mcpay_vault_pickle_ata = next_account_info(accounts_iter)?; // 8
-        let signer_pickle_ata = next_account_info(accounts_iter)?; // 9
-        let clock_in_wallet = next_account_info(accounts_iter)?; // 10
+        let ClockOutAccounts {
+            signer,
+            program_state_pda,
+            asset_state_pda,
+            leaf_delegate,
+            merkle_tree,
+            spl_account_compression_program_id,
+            spl_token_program_id,
+            mcpay_vault_pda,
+            mcpay_vault_pickle_ata,
+            payout_pickle_ata,
+            clock_in_wallet,
+            wallet_state_pda,
+            season_pda,
+            season_record_pda,
+            system_program_id,
+            referrer_wallet_state_pda,
+            pickle_mint,
+            vesting_pda,
+            asset_flag_pda,
+            payout_wallet,
+            spl_associated_token_program_id,
+            rent_payer,
+            remaining_accounts: trailing_accounts,
+        } = ClockOutAccounts::try_from(accounts)?;
+        let accounts_iter = &mut trailing_accounts.iter();
 
         let mut remaining_accounts:  Vec<AccountInfo> = vec![];
         for _n in 0..clock_out_data.proof_length {
             let acct = next_account_info(accounts_iter)?;
             remaining_accounts.push(acct.clone());
         }
+        // without a trailing leaf owner the signer clocks out their own asset
+        let leaf_owner = next_account_info(accounts_iter).unwrap_or(signer);
 
-        if !signer.is_signer {
-            msg!("CERROR: Missing required signature");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        let (program_state, _program_state_bump) = Pubkey::find_program_address(
-            &[b"program-state"], 
+        let mut program_state_data = Self::clock_out_program_state(
             program_id,
-        );
-        assert_true(
-            program_state == *program_state_pda.key,
-            ProgramError::from(McPayError::InvalidProgramStatePDA),
-            "CERROR: Invalid program state pda",
+            program_state_pda,
+            mcpay_vault_pda,
+            mcpay_vault_pickle_ata,
+            pickle_mint,
         )?;
 
-        let program_state_data: ProgramState = ProgramState::try_from_slice(&program_state_pda.data.borrow())?;
-        assert_true(
-            program_state_data.is_initialized,
-            ProgramError::from(McPayError::ProgramStateNotInitialized),
-            "CERROR: Program state not initialized",
+        Self::check_merkle_tree(
+            &program_state_data,
+            merkle_tree,
+            spl_account_compression_program_id,
         )?;
 
-        assert_true(
-            *merkle_tree.key == program_state_data.merkle_tree,
-            ProgramError::from(McPayError::InvalidMerkleTree),
-            "CERROR: Invalid merkle tree",
+        let clock = Clock::get()?;
+        let utime = clock.unix_timestamp;
+
+        let asset_id = mpl_bubblegum::utils::get_asset_id(merkle_tree.key, clock_out_data.nonce);
+        Self::verify_leaf(
+            signer,
+            leaf_owner,
+            leaf_delegate,
+            merkle_tree,
+            spl_account_compression_program_id,
+            &remaining_accounts,
+            asset_id,
+            clock_out_data.nonce,
+            &clock_out_data.root,
+            &clock_out_data.data_hash,
+            &clock_out_data.creator_hash,
+            &clock_out_data.leaf_v2,
         )?;
 
-        assert_true(
-            program_state_data.clock_out_is_enabled == 1,
-            ProgramError::from(McPayError::ClockOutDisabled),
-            "CERROR: Clock out disabled",
+        let asset_state_data = Self::clock_out_asset(
+            program_id,
+            &program_state_data,
+            asset_state_pda,
+            asset_flag_pda,
+            clock_in_wallet,
+            rent_payer,
+            payout_wallet,
+            asset_id,
+            leaf_owner.key,
+            utime,
         )?;
 
-        let asset_id = mpl_bubblegum::utils::get_asset_id(merkle_tree.key, clock_out_data.nonce);
-        let (asset_state, _asset_state_bump) = Pubkey::find_program_address(
-            &[
-                b"asset-state",
-                asset_id.as_ref(),
-            ],
+        Self::settle_clock_out(
             program_id,
-        );
-        assert_true(
-            asset_state == *asset_state_pda.key,
-            ProgramError::from(McPayError::InvalidAssetStatePDA),
-            "CERROR: Invalid asset state pda",
+            &mut program_state_data,
+            &[asset_state_data],
+            signer,
+            rent_payer,
+            leaf_owner.key,
+            program_state_pda,
+            spl_token_program_id,
+            mcpay_vault_pda,
+            mcpay_vault_pickle_ata,
+            payout_pickle_ata,
+            payout_wallet,
+            clock_in_wallet,
+            wallet_state_pda,
+            referrer_wallet_state_pda,
+            season_pda,
+            season_record_pda,
+            vesting_pda,
+            pickle_mint,
+            system_program_id,
+            spl_associated_token_program_id,
+            utime,
         )?;
 
+        Self::refund_asset_rent(&[asset_state_pda], rent_payer)?;
+
+        Ok(())
+    }
+
+    fn process_clock_out_batch(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        clock_out_batch_data: ClockOutBatchData,
+    ) -> ProgramResult {
+        let ClockOutBatchAccounts {
+            signer,
+            program_state_pda,
+            merkle_tree,
+            spl_account_compression_program_id,
+            spl_token_program_id,
+            mcpay_vault_pda,
+            mcpay_vault_pickle_ata,
+            payout_pickle_ata,
+            clock_in_wallet,
+            wallet_state_pda,
+            season_pda,
+            season_record_pda,
+            system_program_id,
+            referrer_wallet_state_pda,
+            pickle_mint,
+            vesting_pda,
+            payout_wallet,
+            spl_associated_token_program_id,
+            rent_payer,
+            remaining_accounts: trailing_accounts,
+        } = ClockOutBatchAccounts::try_from(accounts)?;
+        let accounts_iter = &mut trailing_accounts.iter();
+
         assert_true(
-            *spl_account_compression_program_id.key == spl_account_compression::id(),
-            ProgramError::from(McPayError::InvalidSPLAccountCompressionProgramID),
-            "CERROR: Invalid SPL Account Compression Program ID",
+            !clock_out_batch_data.entries.is_empty(),
+            ProgramError::from(McPayError::InvalidBatch),
+            "CERROR: Empty batch",
         )?;
-        
+
+        // without a leaf owner after the entries the signer clocks out their own assets
+        let entry_accounts: usize = clock_out_batch_data.entries.iter()
+            .map(|clock_out_entry| 3 + clock_out_entry.proof_length as usize)
+            .sum();
         assert_true(
-            *spl_token_program_id.key == spl_token::id(),
-            ProgramError::from(McPayError::InvalidSPLTokenProgramID),
-            "CERROR: Invalid SPL Token Program ID",
+            trailing_accounts.len() == entry_accounts || trailing_accounts.len() == entry_accounts + 1,
+            ProgramError::from(McPayError::InvalidBatch),
+            "CERROR: Batch accounts do not match the entries' proof lengths",
+        )?;
+        let leaf_owner = trailing_accounts.get(entry_accounts).unwrap_or(signer);
+
+        let mut program_state_data = Self::clock_out_program_state(
+            program_id,
+            program_state_pda,
+            mcpay_vault_pda,
+            mcpay_vault_pickle_ata,
+            pickle_mint,
+        )?;
+
+        Self::check_merkle_tree(
+            &program_state_data,
+            merkle_tree,
+            spl_account_compression_program_id,
+        )?;
+
+        let clock = Clock::get()?;
+        let utime = clock.unix_timestamp;
+
+        // every asset in the batch shares the clock in wallet, rent payer and payout wallet
+        let mut asset_states: Vec<AssetState> = vec![];
+        let mut asset_state_pdas: Vec<&AccountInfo> = vec![];
+        for clock_out_entry in clock_out_batch_data.entries.iter() {
+            // asset state, leaf delegate and asset flag, then the entry's proof accounts
+            let asset_state_pda = next_account_info(accounts_iter)?;
+            let leaf_delegate = next_account_info(accounts_iter)?;
+            let asset_flag_pda = next_account_info(accounts_iter)?;
+
+            let mut remaining_accounts:  Vec<AccountInfo> = vec![];
+            for _n in 0..clock_out_entry.proof_length {
+                let acct = next_account_info(accounts_iter)?;
+                remaining_accounts.push(acct.clone());
+            }
+
+            let asset_id = mpl_bubblegum::utils::get_asset_id(merkle_tree.key, clock_out_entry.nonce);
+            Self::verify_leaf(
+                signer,
+                leaf_owner,
+                leaf_delegate,
+                merkle_tree,
+                spl_account_compression_program_id,
+                &remaining_accounts,
+                asset_id,
+                clock_out_entry.nonce,
+                &clock_out_entry.root,
+                &clock_out_entry.data_hash,
+                &clock_out_entry.creator_hash,
+                &clock_out_entry.leaf_v2,
+            )?;
+
+            let asset_state_data = Self::clock_out_asset(
+                program_id,
+                &program_state_data,
+                asset_state_pda,
+                asset_flag_pda,
+                clock_in_wallet,
+                rent_payer,
+                payout_wallet,
+                asset_id,
+                leaf_owner.key,
+                utime,
+            )?;
+            asset_states.push(asset_state_data);
+            asset_state_pdas.push(asset_state_pda);
+        }
+
+        Self::settle_clock_out(
+            program_id,
+            &mut program_state_data,
+            &asset_states,
+            signer,
+            rent_payer,
+            leaf_owner.key,
+            program_state_pda,
+            spl_token_program_id,
+            mcpay_vault_pda,
+            mcpay_vault_pickle_ata,
+            payout_pickle_ata,
+            payout_wallet,
+            clock_in_wallet,
+            wallet_state_pda,
+            referrer_wallet_state_pda,
+            season_pda,
+            season_record_pda,
+            vesting_pda,
+            pickle_mint,
+            system_program_id,
+            spl_associated_token_program_id,
+            utime,
+        )?;
+
+        Self::refund_asset_rent(&asset_state_pdas, rent_payer)?;
+
+        Ok(())
+    }
+
+    fn process_clock_in_core(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        clock_in_core_data: ClockInCoreData,
+    ) -> ProgramResult {
+        let ClockInCoreAccounts {
+            signer,
+            program_state_pda,
+            asset_state_pda,
+            asset,
+            collection,
+            mpl_core_program_id,
+            system_program_id,
+            wallet_state_pda,
+            referrer_wallet_state_pda,
+            mcpay_vault_pda,
+            mcdegens_treasury,
+            asset_flag_pda,
+            rent_payer,
+            ..
+        } = ClockInCoreAccounts::try_from(accounts)?;
+
+        let mut program_state_data = Self::clock_in_program_state(
+            program_id,
+            program_state_pda,
+            mcpay_vault_pda,
+            mcdegens_treasury,
         )?;
 
+        let asset_owner = Self::core_asset_owner(
+            &program_state_data,
+            asset,
+            collection,
+        )?;
         assert_true(
-            *mcpay_vault_pda.key == program_state_data.mcpay_vault_pda,
-            ProgramError::from(McPayError::InvalidMcPayVaultPDA),
-            "CERROR: Invalid mcpay vault pda",
+            asset_owner == *signer.key,
+            ProgramError::from(McPayError::InvalidAssetOwner),
+            "CERROR: Signer is not the asset owner",
+        )?;
+        msg!("asset_id {}", asset.key);
+
+        let mut wallet_state_data = Self::clock_in_wallet_state(
+            program_id,
+            signer.key,
+            rent_payer,
+            wallet_state_pda,
+            referrer_wallet_state_pda,
+            system_program_id,
+            &clock_in_core_data.referrer,
+        )?;
+
+        let clock = Clock::get()?;
+        let utime = clock.unix_timestamp;
+
+        let fee_lamports = Self::clock_in_asset(
+            program_id,
+            &mut program_state_data,
+            &mut wallet_state_data,
+            signer.key,
+            rent_payer,
+            system_program_id,
+            asset_state_pda,
+            asset_flag_pda,
+            *asset.key,
+            ASSET_KIND_CORE,
+            clock_in_core_data.asset_state_bump,
+            clock_in_core_data.asset_flag_bump,
+            clock_in_core_data.level,
+            &clock_in_core_data.payout_recipient,
+            utime,
+        )?;
+
+        // the vault pda holds the freeze delegate for as long as the asset is clocked in
+        msg!("Freezing Core Asset");
+        mpl_core::instructions::AddPluginV1CpiBuilder::new(mpl_core_program_id)
+            .asset(asset)
+            .collection(Some(collection))
+            .payer(rent_payer)
+            .authority(Some(signer))
+            .system_program(system_program_id)
+            .plugin(mpl_core::types::Plugin::FreezeDelegate(mpl_core::types::FreezeDelegate { frozen: true }))
+            .init_authority(mpl_core::types::PluginAuthority::Address { address: *mcpay_vault_pda.key })
+            .invoke()?;
+
+        wallet_state_data.store(wallet_state_pda)?;
+        program_state_data.store(program_state_pda)?;
+
+        Self::collect_clock_in_fee(
+            &program_state_data,
+            fee_lamports,
+            rent_payer,
+            mcpay_vault_pda,
+            mcdegens_treasury,
+            system_program_id,
+        )?;
+
+        Ok(())
+    }
+
+    fn process_clock_out_core(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let ClockOutCoreAccounts {
+            signer,
+            program_state_pda,
+            asset_state_pda,
+            asset,
+            collection,
+            mpl_core_program_id,
+            spl_token_program_id,
+            mcpay_vault_pda,
+            mcpay_vault_pickle_ata,
+            payout_pickle_ata,
+            clock_in_wallet,
+            wallet_state_pda,
+            season_pda,
+            season_record_pda,
+            system_program_id,
+            referrer_wallet_state_pda,
+            pickle_mint,
+            vesting_pda,
+            asset_flag_pda,
+            payout_wallet,
+            spl_associated_token_program_id,
+            rent_payer,
+            ..
+        } = ClockOutCoreAccounts::try_from(accounts)?;
+
+        let mut program_state_data = Self::clock_out_program_state(
+            program_id,
+            program_state_pda,
+            mcpay_vault_pda,
+            mcpay_vault_pickle_ata,
+            pickle_mint,
+        )?;
+
+        let asset_owner = Self::core_asset_owner(
+            &program_state_data,
+            asset,
+            collection,
         )?;
-        
         assert_true(
-            *mcpay_vault_pickle_ata.key == program_state_data.mcpay_vault_pickle_ata,
-            ProgramError::from(McPayError::InvalidMcPayVaultPickleATA),
-            "CERROR: Invalid mcpay vault pickle ata",
+            asset_owner == *signer.key,
+            ProgramError::from(McPayError::InvalidAssetOwner),
+            "CERROR: Signer is not the asset owner",
+        )?;
+
+        let clock = Clock::get()?;
+        let utime = clock.unix_timestamp;
+
+        let asset_state_data = Self::clock_out_asset(
+            program_id,
+            &program_state_data,
+            asset_state_pda,
+            asset_flag_pda,
+            clock_in_wallet,
+            rent_payer,
+            payout_wallet,
+            *asset.key,
+            &asset_owner,
+            utime,
+        )?;
+
+        msg!("Thawing Core Asset");
+        mpl_core::instructions::UpdatePluginV1CpiBuilder::new(mpl_core_program_id)
+            .asset(asset)
+            .collection(Some(collection))
+            .payer(signer)
+            .authority(Some(mcpay_vault_pda))
+            .system_program(system_program_id)
+            .plugin(mpl_core::types::Plugin::FreezeDelegate(mpl_core::types::FreezeDelegate { frozen: false }))
+            .invoke_signed(&[&[b"mcpay-vault", &[program_state_data.mcpay_vault_bump]]])?;
+
+        // removed so the next clock in can add it again
+        mpl_core::instructions::RemovePluginV1CpiBuilder::new(mpl_core_program_id)
+            .asset(asset)
+            .collection(Some(collection))
+            .payer(signer)
+            .authority(Some(signer))
+            .system_program(system_program_id)
+            .plugin_type(mpl_core::types::PluginType::FreezeDelegate)
+            .invoke()?;
+
+        Self::settle_clock_out(
+            program_id,
+            &mut program_state_data,
+            &[asset_state_data],
+            signer,
+            rent_payer,
+            &asset_owner,
+            program_state_pda,
+            spl_token_program_id,
+            mcpay_vault_pda,
+            mcpay_vault_pickle_ata,
+            payout_pickle_ata,
+            payout_wallet,
+            clock_in_wallet,
+            wallet_state_pda,
+            referrer_wallet_state_pda,
+            season_pda,
+            season_record_pda,
+            vesting_pda,
+            pickle_mint,
+            system_program_id,
+            spl_associated_token_program_id,
+            utime,
+        )?;
+
+        Self::refund_asset_rent(&[asset_state_pda], rent_payer)?;
+
+        Ok(())
+    }
+
+    fn process_clock_in_legacy(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        clock_in_legacy_data: ClockInLegacyData,
+    ) -> ProgramResult {
+        let ClockInLegacyAccounts {
+            signer,
+            program_state_pda,
+            asset_state_pda,
+            mint,
+            token_account,
+            metadata_pda,
+            edition_pda,
+            token_metadata_program_id,
+            spl_token_program_id,
+            system_program_id,
+            wallet_state_pda,
+            referrer_wallet_state_pda,
+            mcpay_vault_pda,
+            mcdegens_treasury,
+            asset_flag_pda,
+            rent_payer,
+            ..
+        } = ClockInLegacyAccounts::try_from(accounts)?;
+
+        let mut program_state_data = Self::clock_in_program_state(
+            program_id,
+            program_state_pda,
+            mcpay_vault_pda,
+            mcdegens_treasury,
+        )?;
+
+        let asset_owner = Self::legacy_asset_owner(
+            &program_state_data,
+            mint,
+            token_account,
+            metadata_pda,
+            edition_pda,
         )?;
- 
-        let signer_pickle = get_associated_token_address(
-            &signer.key, 
-            &program_state_data.pickle_mint
-        );
         assert_true(
-            *signer_pickle_ata.key == signer_pickle,
-            ProgramError::from(McPayError::InvalidATA),
-            "CERROR: Invalid signer pickle ata",
+            asset_owner == *signer.key,
+            ProgramError::from(McPayError::InvalidAssetOwner),
+            "CERROR: Signer is not the asset owner",
         )?;
-        
-        let mcpay_vault_pickle_ata_data = Account::unpack(&mcpay_vault_pickle_ata.try_borrow_data()?)?;
-        let asset_state_data: AssetState = AssetState::try_from_slice(&asset_state_pda.data.borrow())?;
-        assert_true(                    
-            mcpay_vault_pickle_ata_data.amount >= asset_state_data.chips_due,
-            ProgramError::from(McPayError::InsufficientVaultPickle),
-            "CERROR: Insufficient funds in Pickle Vault",
+        msg!("asset_id {}", mint.key);
+
+        let mut wallet_state_data = Self::clock_in_wallet_state(
+            program_id,
+            signer.key,
+            rent_payer,
+            wallet_state_pda,
+            referrer_wallet_state_pda,
+            system_program_id,
+            &clock_in_legacy_data.referrer,
         )?;
 
-        let leaf = mpl_bubblegum::types::LeafSchema::V1 { 
-            id: asset_id, 
-            owner: *signer.key, 
-            delegate: *leaf_delegate.key, 
-            nonce: clock_out_data.nonce, 
-            data_hash: clock_out_data.data_hash.to_bytes(), 
-            creator_hash: clock_out_data.creator_hash.to_bytes(),
-        };
+        let clock = Clock::get()?;
+        let utime = clock.unix_timestamp;
 
-        let verify_leaf_cpi = mpl_bubblegum::instructions::VerifyLeafCpi::new(
-            spl_account_compression_program_id, 
-            mpl_bubblegum::instructions::VerifyLeafCpiAccounts {
-                merkle_tree,
-            }, 
-            mpl_bubblegum::instructions::VerifyLeafInstructionArgs {
-                index: clock_out_data.nonce.try_into().unwrap(),
-                leaf: leaf.hash(),
-                root: clock_out_data.root.to_bytes(),
-            }
-        );
-        verify_leaf_cpi.invoke_with_remaining_accounts(
-            remaining_accounts
-                .iter()
-                .map(|account| (account, false, false))
-                .collect::<Vec<_>>()
-                .as_slice()
+        let fee_lamports = Self::clock_in_asset(
+            program_id,
+            &mut program_state_data,
+            &mut wallet_state_data,
+            signer.key,
+            rent_payer,
+            system_program_id,
+            asset_state_pda,
+            asset_flag_pda,
+            *mint.key,
+            ASSET_KIND_LEGACY,
+            clock_in_legacy_data.asset_state_bump,
+            clock_in_legacy_data.asset_flag_bump,
+            clock_in_legacy_data.level,
+            &clock_in_legacy_data.payout_recipient,
+            utime,
         )?;
 
-        if !asset_state_pda.data_is_empty() {
-            assert_true(
-                *clock_in_wallet.key == asset_state_data.clock_in_wallet,
-                ProgramError::from(McPayError::InvalidClockInWallet),
-                "CERROR: Invalid clock in wallet",
-            )?;
-                
-            let clock = Clock::get()?;
-            let utime = clock.unix_timestamp;
-            if asset_state_data.clock_out_utime <= utime {
-                msg!("Transferring Pickle");
-                let transfer_vault_pickle_ix = spl_token::instruction::transfer(
-                    spl_token_program_id.key,
-                    mcpay_vault_pickle_ata.key,
-                    signer_pickle_ata.key,
-                    &mcpay_vault_pda.key,
-                    &[],
-                    asset_state_data.chips_due,
-                )?;
-                invoke_signed(
-                    &transfer_vault_pickle_ix,
-                    &[
-                        mcpay_vault_pickle_ata.clone(),
-                        signer_pickle_ata.clone(),
-                        mcpay_vault_pda.clone(),
-                    ],
-                    &[&[b"mcpay-vault", &[program_state_data.mcpay_vault_bump]]],
-                )?;
+        // the vault pda becomes the token delegate and freezes the nft in place
+        msg!("Freezing NFT");
+        let approve_ix = spl_token::instruction::approve(
+            spl_token_program_id.key,
+            token_account.key,
+            mcpay_vault_pda.key,
+            signer.key,
+            &[],
+            1,
+        )?;
+        invoke(
+            &approve_ix,
+            &[
+                token_account.clone(),
+                mcpay_vault_pda.clone(),
+                signer.clone(),
+            ],
+        )?;
+        mpl_token_metadata::instructions::FreezeDelegatedAccountCpi::new(
+            token_metadata_program_id,
+            mpl_token_metadata::instructions::FreezeDelegatedAccountCpiAccounts {
+                delegate: mcpay_vault_pda,
+                token_account,
+                edition: edition_pda,
+                mint,
+                token_program: spl_token_program_id,
+            },
+        ).invoke_signed(&[&[b"mcpay-vault", &[program_state_data.mcpay_vault_bump]]])?;
 
-                msg!("Closing Asset State");
-                **clock_in_wallet.try_borrow_mut_lamports()? = clock_in_wallet
-                    .lamports()
-                    .checked_add(asset_state_pda.lamports())
-                    .ok_or(McPayError::AmountOverflow)?;
-                **asset_state_pda.try_borrow_mut_lamports()? = 0;
-                *asset_state_pda.try_borrow_mut_data()? = &mut [];
-            } else {
-                msg!("CERROR: To soon");
-                return Err(McPayError::TooSoon.into());    
-            }
-        } else {
-            msg!("CERROR: Asset not clocked in");
-            return Err(McPayError::NotClockedIn.into());
-        }
+        wallet_state_data.store(wallet_state_pda)?;
+        program_state_data.store(program_state_pda)?;
+
+        Self::collect_clock_in_fee(
+            &program_state_data,
+            fee_lamports,
+            rent_payer,
+            mcpay_vault_pda,
+            mcdegens_treasury,
+            system_program_id,
+        )?;
 
         Ok(())
     }
 
-    fn process_update_state(
+    fn process_clock_out_legacy(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        update_state_data: UpdateStateData,
     ) -> ProgramResult {
-        let accounts_iter = &mut accounts.iter();
-        let signer = next_account_info(accounts_iter)?; // 0
-        let program_state_pda = next_account_info(accounts_iter)?; // 1
-        let system_program_id = next_account_info(accounts_iter)?; // 2
+        let ClockOutLegacyAccounts {
+            signer,
+            program_state_pda,
+            asset_state_pda,
+            mint,
+            token_account,
+            metadata_pda,
+            edition_pda,
+            token_metadata_program_id,
+            spl_token_program_id,
+            mcpay_vault_pda,
+            mcpay_vault_pickle_ata,
+            payout_pickle_ata,
+            clock_in_wallet,
+            wallet_state_pda,
+            season_pda,
+            season_record_pda,
+            system_program_id,
+            referrer_wallet_state_pda,
+            pickle_mint,
+            vesting_pda,
+            asset_flag_pda,
+            payout_wallet,
+            spl_associated_token_program_id,
+            rent_payer,
+            ..
+        } = ClockOutLegacyAccounts::try_from(accounts)?;
 
-        let base_account = Pubkey::from_str("25hZAxGdWsP158Y8NG9eZbDwiS5bsku5UEx7ZLzVGhta").unwrap();
-        let base2_account = Pubkey::from_str("Aq3Nm72sY2hJScVQ89rzMKF22f3zUCbF2eioUhMeJuDj").unwrap();
+        let mut program_state_data = Self::clock_out_program_state(
+            program_id,
+            program_state_pda,
+            mcpay_vault_pda,
+            mcpay_vault_pickle_ata,
+            pickle_mint,
+        )?;
 
-        if !signer.is_signer {
-            msg!("CERROR: Missing required signature");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
+        let asset_owner = Self::legacy_asset_owner(
+            &program_state_data,
+            mint,
+            token_account,
+            metadata_pda,
+            edition_pda,
+        )?;
         assert_true(
-            (*signer.key == base_account) || (*signer.key == base2_account),
-            ProgramError::from(ProgramError::MissingRequiredSignature),
-            "CERROR: Invalid signature",
+            asset_owner == *signer.key,
+            ProgramError::from(McPayError::InvalidAssetOwner),
+            "CERROR: Signer is not the asset owner",
+        )?;
+
+        let clock = Clock::get()?;
+        let utime = clock.unix_timestamp;
+
+        let asset_state_data = Self::clock_out_asset(
+            program_id,
+            &program_state_data,
+            asset_state_pda,
+            asset_flag_pda,
+            clock_in_wallet,
+            rent_payer,
+            payout_wallet,
+            *mint.key,
+            &asset_owner,
+            utime,
+        )?;
+
+        msg!("Thawing NFT");
+        mpl_token_metadata::instructions::ThawDelegatedAccountCpi::new(
+            token_metadata_program_id,
+            mpl_token_metadata::instructions::ThawDelegatedAccountCpiAccounts {
+                delegate: mcpay_vault_pda,
+                token_account,
+                edition: edition_pda,
+                mint,
+                token_program: spl_token_program_id,
+            },
+        ).invoke_signed(&[&[b"mcpay-vault", &[program_state_data.mcpay_vault_bump]]])?;
+        let revoke_ix = spl_token::instruction::revoke(
+            spl_token_program_id.key,
+            token_account.key,
+            signer.key,
+            &[],
         )?;
+        invoke(
+            &revoke_ix,
+            &[
+                token_account.clone(),
+                signer.clone(),
+            ],
+        )?;
+
+        Self::settle_clock_out(
+            program_id,
+            &mut program_state_data,
+            &[asset_state_data],
+            signer,
+            rent_payer,
+            &asset_owner,
+            program_state_pda,
+            spl_token_program_id,
+            mcpay_vault_pda,
+            mcpay_vault_pickle_ata,
+            payout_pickle_ata,
+            payout_wallet,
+            clock_in_wallet,
+            wallet_state_pda,
+            referrer_wallet_state_pda,
+            season_pda,
+            season_record_pda,
+            vesting_pda,
+            pickle_mint,
+            system_program_id,
+            spl_associated_token_program_id,
+            utime,
+        )?;
+
+        Self::refund_asset_rent(&[asset_state_pda], rent_payer)?;
+
+        Ok(())
+    }
+
+    fn process_update_state(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        update_state_data: UpdateStateData,
+    ) -> ProgramResult {
+        let UpdateStateAccounts {
+            signer,
+            program_state_pda,
+            system_program_id,
+            ..
+        } = UpdateStateAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
 
         let (program_state, program_state_bump) = Pubkey::find_program_address(&[b"program-state"], program_id);
         assert_true(
@@ -499,27 +1473,22 @@ impl Processor {
             "CERROR: Invalid program state pda",
         )?;
 
-        assert_true(
-            *system_program_id.key == solana_program::system_program::id(),
-            ProgramError::from(McPayError::InvalidSystemProgramID),
-            "CERROR: Invalid System Program ID",
-        )?;
-
         if update_state_data.new_clock_in_is_enabled < 2 || 
             update_state_data.new_clock_out_is_enabled < 2 ||
-            update_state_data.new_merkle_tree != Pubkey::from_str("11111111111111111111111111111111").unwrap() ||
+            update_state_data.new_merkle_tree != Pubkey::default() ||
             update_state_data.new_level_one_rate > 0 ||
             update_state_data.new_level_seven_rate > 0 ||
             update_state_data.new_level_thirty_rate > 0 ||
-            update_state_data.new_pickle_mint != Pubkey::from_str("11111111111111111111111111111111").unwrap() || 
-            update_state_data.new_mcdegens_treasury != Pubkey::from_str("11111111111111111111111111111111").unwrap() ||
-            update_state_data.new_mcdegens_pickle_ata != Pubkey::from_str("11111111111111111111111111111111").unwrap()
+            update_state_data.new_pickle_mint != Pubkey::default() || 
+            update_state_data.new_mcdegens_treasury != Pubkey::default() ||
+            update_state_data.new_mcdegens_pickle_ata != Pubkey::default()
             {                
 
-            if program_state_pda.data_is_empty()
+            let is_new = program_state_pda.data_is_empty();
+            if is_new
             {
                 msg!("Creating Program State Account");
-                let program_state_size = 1 + 1 + 1 + 32 + 8 + 8 + 8 + 32 + 32 + 1 + 32 + 32 + 32;
+                let program_state_size = ProgramState::LEN;
                 invoke_signed(
                     &system_instruction::create_account(
                         signer.key,
@@ -538,9 +1507,18 @@ impl Processor {
                         &[program_state_bump],
                     ]],
                 )?;
+            } else if program_state_pda.data_len() < ProgramState::LEN
+                || program_state_pda.data.borrow().get(8) != Some(&ProgramState::VERSION) {
+                // a program state written under an older layout is migrated to the current one first
+                msg!("Migrating Program State Account");
+                Self::migrate_account::<ProgramState>(program_id, signer, program_state_pda, system_program_id)?;
             }
             
-            let mut program_state: ProgramState = ProgramState::try_from_slice(&program_state_pda.data.borrow())?;
+            let mut program_state: ProgramState = if is_new {
+                ProgramState::init(program_state_pda, program_id)?
+            } else {
+                ProgramState::load(program_state_pda, program_id)?
+            };
             if program_state.is_initialized {
                 if update_state_data.new_clock_in_is_enabled < 2 {
                     program_state.clock_in_is_enabled = update_state_data.new_clock_in_is_enabled;
@@ -548,7 +1526,7 @@ impl Processor {
                 if update_state_data.new_clock_out_is_enabled < 2 {
                     program_state.clock_out_is_enabled = update_state_data.new_clock_out_is_enabled;
                 }
-                if update_state_data.new_merkle_tree != Pubkey::from_str("11111111111111111111111111111111").unwrap() {
+                if update_state_data.new_merkle_tree != Pubkey::default() {
                     program_state.merkle_tree = update_state_data.new_merkle_tree;
                 }
                 if update_state_data.new_level_one_rate > 0 {
@@ -560,15 +1538,16 @@ impl Processor {
                 if update_state_data.new_level_thirty_rate > 0 {
                     program_state.level_thirty_rate = update_state_data.new_level_thirty_rate;
                 }
-                if update_state_data.new_pickle_mint != Pubkey::from_str("11111111111111111111111111111111").unwrap() {
+                if update_state_data.new_pickle_mint != Pubkey::default() {
                     program_state.pickle_mint = update_state_data.new_pickle_mint;
                 }
-                if update_state_data.new_mcdegens_treasury != Pubkey::from_str("11111111111111111111111111111111").unwrap() {
+                if update_state_data.new_mcdegens_treasury != Pubkey::default() {
                     program_state.mcdegens_treasury = update_state_data.new_mcdegens_treasury;
                 }
-                if update_state_data.new_mcdegens_pickle_ata != Pubkey::from_str("11111111111111111111111111111111").unwrap() {
+                if update_state_data.new_mcdegens_pickle_ata != Pubkey::default() {
                     program_state.mcdegens_pickle_ata = update_state_data.new_mcdegens_pickle_ata;
                 }
+                program_state.program_state_bump = program_state_bump;
            } else {
                 let (mcpay_vault_pda, mcpay_vault_bump) = Pubkey::find_program_address(&[b"mcpay-vault"], program_id);
                 let mcpay_vault_pickle_ata = get_associated_token_address(&mcpay_vault_pda, &update_state_data.new_pickle_mint);
@@ -586,8 +1565,9 @@ impl Processor {
                 program_state.mcpay_vault_pickle_ata = mcpay_vault_pickle_ata;
                 program_state.mcdegens_treasury = update_state_data.new_mcdegens_treasury;
                 program_state.mcdegens_pickle_ata = update_state_data.new_mcdegens_pickle_ata;
+                program_state.program_state_bump = program_state_bump;
             }
-            program_state.serialize(&mut &mut program_state_pda.data.borrow_mut()[..])?;
+            program_state.store(program_state_pda)?;
         } else {
             msg!("CERROR: No updates indicated");
             return Err(McPayError::NoUpdatesIndicated.into());
@@ -600,22 +1580,13 @@ impl Processor {
         program_id: &Pubkey,
         accounts: &[AccountInfo],
     ) -> ProgramResult {
-        let accounts_iter = &mut accounts.iter();
-        let signer = next_account_info(accounts_iter)?; // 0
-        let program_state_pda = next_account_info(accounts_iter)?; // 1
-
-        let base_account = Pubkey::from_str("25hZAxGdWsP158Y8NG9eZbDwiS5bsku5UEx7ZLzVGhta").unwrap();
-        let base2_account = Pubkey::from_str("Aq3Nm72sY2hJScVQ89rzMKF22f3zUCbF2eioUhMeJuDj").unwrap();
+        let CloseProgramStateAccounts {
+            signer,
+            program_state_pda,
+            ..
+        } = CloseProgramStateAccounts::try_from(accounts)?;
 
-        if !signer.is_signer {
-            msg!("CERROR: Missing required signature");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-        assert_true(
-            (*signer.key == base_account) || (*signer.key == base2_account),
-            ProgramError::from(ProgramError::MissingRequiredSignature),
-            "CERROR: Invalid signature",
-        )?;
+        assert_admin(signer)?;
 
         let (program_state, _program_state_bump) = Pubkey::find_program_address(&[b"program-state"], program_id);
         assert_true(
@@ -640,55 +1611,71 @@ impl Processor {
         accounts: &[AccountInfo],
         transfer_pickle_data: TransferPickleData,
     ) -> ProgramResult {
-        let accounts_iter = &mut accounts.iter();
-        let signer = next_account_info(accounts_iter)?; // 0
-        let program_state_pda = next_account_info(accounts_iter)?; // 1
-        let mcpay_vault_pda= next_account_info(accounts_iter)?; // 2
-        let mcpay_vault_pickle_ata= next_account_info(accounts_iter)?; // 3
-        let mcdegens_pickle_ata = next_account_info(accounts_iter)?; // 4
-        let spl_token_program_id = next_account_info(accounts_iter)?; // 5
-
-        let base_account = Pubkey::from_str("25hZAxGdWsP158Y8NG9eZbDwiS5bsku5UEx7ZLzVGhta").unwrap();
-        let base2_account = Pubkey::from_str("Aq3Nm72sY2hJScVQ89rzMKF22f3zUCbF2eioUhMeJuDj").unwrap();
-
-        if !signer.is_signer {
-            msg!("CERROR: Missing required signature");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-        assert_true(
-            (*signer.key == base_account) || (*signer.key == base2_account),
-            ProgramError::from(ProgramError::MissingRequiredSignature),
-            "CERROR: Invalid signature",
-        )?;
+        let TransferPickleAccounts {
+            signer,
+            program_state_pda,
+            mcpay_vault_pda,
+            mcpay_vault_pickle_ata,
+            treasury_pickle_ata,
+            spl_token_program_id,
+            remaining_accounts: trailing_accounts,
+        } = TransferPickleAccounts::try_from(accounts)?;
+        let accounts_iter = &mut trailing_accounts.iter();
 
-        let (program_state, _program_state_bump) = Pubkey::find_program_address(&[b"program-state"], program_id);
-        assert_true(
-            program_state == *program_state_pda.key,
-            ProgramError::from(McPayError::InvalidProgramStatePDA),
-            "CERROR: Invalid program state pda",
-        )?;
+        assert_admin(signer)?;
 
-        let program_state: ProgramState = ProgramState::try_from_slice(&program_state_pda.data.borrow())?;
+        let program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
         if !program_state.is_initialized {
             msg!("CERROR: Program state not initialized");
             return Err(McPayError::ProgramStateNotInitialized.into());            
         }
 
         assert_true(
-            program_state.mcdegens_pickle_ata == *mcdegens_pickle_ata.key,
-            ProgramError::from(McPayError::InvalidMcDegensPickleATA),
-            "CERROR: Invalid mcdegens pickle ATA",
+            *mcpay_vault_pda.key == program_state.mcpay_vault_pda,
+            ProgramError::from(McPayError::InvalidMcPayVaultPDA),
+            "CERROR: Invalid mcpay vault pda",
         )?;
 
+        // treasury type 0 is the mcdegens pickle ata, the rest are registered treasuries
+        if transfer_pickle_data.treasury_type == 0 {
+            assert_true(
+                program_state.mcdegens_pickle_ata == *treasury_pickle_ata.key,
+                ProgramError::from(McPayError::InvalidMcDegensPickleATA),
+                "CERROR: Invalid mcdegens pickle ATA",
+            )?;
+        } else {
+            let treasury_pda = next_account_info(accounts_iter)?; // 6
+
+            let (treasury_state, _treasury_bump) = Self::load_or_find_pda::<TreasuryState>(
+                program_id,
+                treasury_pda,
+                &[
+                    b"treasury",
+                    &[transfer_pickle_data.treasury_type],
+                ],
+                McPayError::InvalidTreasuryPDA,
+            )?;
+            let Some(treasury_state) = treasury_state else {
+                msg!("CERROR: Treasury not found");
+                return Err(McPayError::TreasuryNotFound.into());
+            };
+            assert_true(
+                treasury_state.pickle_ata == *treasury_pickle_ata.key,
+                ProgramError::from(McPayError::InvalidTreasuryPickleATA),
+                "CERROR: Invalid treasury pickle ATA",
+            )?;
+        }
+
         assert_true(
             program_state.mcpay_vault_pickle_ata == *mcpay_vault_pickle_ata.key,
             ProgramError::from(McPayError::InvalidMcPayVaultPickleATA),
             "CERROR: Invalid mcpay vault pickle ata",
         )?;
         
-        let mcpay_vault_pickle_ata_data = Account::unpack(&mcpay_vault_pickle_ata.try_borrow_data()?)?;
+        // vesting payouts, season pools and referral rewards stay in the vault until claimed
+        let mcpay_vault_pickle_ata_data = unpack_token_account(mcpay_vault_pickle_ata)?;
         assert_true(                    
-            mcpay_vault_pickle_ata_data.amount >= transfer_pickle_data.chips,
+            Self::unreserved_chips(&program_state, mcpay_vault_pickle_ata_data.amount) >= transfer_pickle_data.chips,
             ProgramError::from(McPayError::InsufficientVaultPickle),
             "CERROR: Insufficient funds in Pickle Vault",
         )?;
@@ -697,7 +1684,7 @@ impl Processor {
         let transfer_pickle_ix = spl_token::instruction::transfer(
             spl_token_program_id.key,
             mcpay_vault_pickle_ata.key,
-            mcdegens_pickle_ata.key,
+            treasury_pickle_ata.key,
             &mcpay_vault_pda.key,
             &[],
             transfer_pickle_data.chips,
@@ -706,7 +1693,7 @@ impl Processor {
             &transfer_pickle_ix,
             &[
                 mcpay_vault_pickle_ata.clone(),
-                mcdegens_pickle_ata.clone(),
+                treasury_pickle_ata.clone(),
                 mcpay_vault_pda.clone(),
             ],
             &[&[
@@ -723,43 +1710,27 @@ impl Processor {
         accounts: &[AccountInfo],
         transfer_sol_data: TransferSOLData,
     ) -> ProgramResult {
-        let accounts_iter = &mut accounts.iter();
-        let signer = next_account_info(accounts_iter)?; // 0
-        let program_state_pda = next_account_info(accounts_iter)?; // 1
-        let mcpay_vault_pda = next_account_info(accounts_iter)?; // 2
-        let mcdegens_treasury = next_account_info(accounts_iter)?; // 3
-        let system_program_id = next_account_info(accounts_iter)?; // 4
+        let TransferSOLAccounts {
+            signer,
+            program_state_pda,
+            mcpay_vault_pda,
+            mcdegens_treasury,
+            system_program_id,
+            ..
+        } = TransferSOLAccounts::try_from(accounts)?;
 
-        let base_account = Pubkey::from_str("25hZAxGdWsP158Y8NG9eZbDwiS5bsku5UEx7ZLzVGhta").unwrap();
-        let base2_account = Pubkey::from_str("Aq3Nm72sY2hJScVQ89rzMKF22f3zUCbF2eioUhMeJuDj").unwrap();
+        assert_admin(signer)?;
 
-        if !signer.is_signer {
-            msg!("CERROR: Missing required signature");
-            return Err(ProgramError::MissingRequiredSignature);
+        let program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
         }
+
         assert_true(
-            (*signer.key == base_account) || (*signer.key == base2_account),
-            ProgramError::from(ProgramError::MissingRequiredSignature),
-            "CERROR: Invalid signature",
-        )?;
-
-        let (program_state, _program_state_bump) = Pubkey::find_program_address(&[b"program-state"], program_id);
-        assert_true(
-            program_state == *program_state_pda.key,
-            ProgramError::from(McPayError::InvalidProgramStatePDA),
-            "CERROR: Invalid program state pda",
-        )?;
-        
-        let program_state: ProgramState = ProgramState::try_from_slice(&program_state_pda.data.borrow())?;
-        if !program_state.is_initialized {
-            msg!("CERROR: Program state not initialized");
-            return Err(McPayError::ProgramStateNotInitialized.into());            
-        }
-
-        assert_true(
-            program_state.mcpay_vault_pda == *mcpay_vault_pda.key,
-            ProgramError::from(McPayError::InvalidMcPayVaultPDA),
-            "CERROR: Invalid mcpay vault pda",
+            program_state.mcpay_vault_pda == *mcpay_vault_pda.key,
+            ProgramError::from(McPayError::InvalidMcPayVaultPDA),
+            "CERROR: Invalid mcpay vault pda",
         )?;
 
         assert_true(
@@ -797,4 +1768,2851 @@ impl Processor {
 
         Ok(())
     }
+    fn process_update_bonus_brackets(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        update_bonus_brackets_data: UpdateBonusBracketsData,
+    ) -> ProgramResult {
+        let UpdateBonusBracketsAccounts {
+            signer,
+            program_state_pda,
+            ..
+        } = UpdateBonusBracketsAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        for bonus_bracket in update_bonus_brackets_data.bonus_brackets.iter() {
+            assert_true(
+                bonus_bracket.bonus_bps <= 10_000,
+                ProgramError::from(McPayError::InvalidBonusBracket),
+                "CERROR: Invalid bonus bracket",
+            )?;
+        }
+
+        program_state.bonus_brackets = update_bonus_brackets_data.bonus_brackets;
+        program_state.store(program_state_pda)?;
+
+        Ok(())
+    }
+
+    fn process_update_emission_schedule(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        update_emission_schedule_data: UpdateEmissionScheduleData,
+    ) -> ProgramResult {
+        let UpdateEmissionScheduleAccounts {
+            signer,
+            program_state_pda,
+            ..
+        } = UpdateEmissionScheduleAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        assert_true(
+            update_emission_schedule_data.emission_decay_bps <= 10_000 &&
+                update_emission_schedule_data.emission_epoch_seconds >= 0 &&
+                (update_emission_schedule_data.emission_decay_bps == 0 || update_emission_schedule_data.emission_epoch_seconds > 0),
+            ProgramError::from(McPayError::InvalidEmissionSchedule),
+            "CERROR: Invalid emission schedule",
+        )?;
+
+        program_state.emission_start_utime = update_emission_schedule_data.emission_start_utime;
+        program_state.emission_epoch_seconds = update_emission_schedule_data.emission_epoch_seconds;
+        program_state.emission_decay_bps = update_emission_schedule_data.emission_decay_bps;
+        program_state.emission_floor_rate = update_emission_schedule_data.emission_floor_rate;
+        program_state.store(program_state_pda)?;
+
+        Ok(())
+    }
+
+    fn process_update_emission_cap(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        update_emission_cap_data: UpdateEmissionCapData,
+    ) -> ProgramResult {
+        let UpdateEmissionCapAccounts {
+            signer,
+            program_state_pda,
+            ..
+        } = UpdateEmissionCapAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        // a cap always counts over an epoch, there is no lifetime cap
+        assert_true(
+            update_emission_cap_data.emission_cap_epoch_seconds >= 0 &&
+            (update_emission_cap_data.emission_cap == 0 || update_emission_cap_data.emission_cap_epoch_seconds > 0),
+            ProgramError::from(McPayError::InvalidEmissionCap),
+            "CERROR: Invalid emission cap",
+        )?;
+
+        // a new epoch length restarts the count at the next clock in
+        if update_emission_cap_data.emission_cap_epoch_seconds != program_state.emission_cap_epoch_seconds {
+            program_state.emission_cap_epoch_start_utime = 0;
+            program_state.emission_committed = 0;
+        }
+        program_state.emission_cap = update_emission_cap_data.emission_cap;
+        program_state.emission_cap_epoch_seconds = update_emission_cap_data.emission_cap_epoch_seconds;
+        program_state.store(program_state_pda)?;
+
+        Ok(())
+    }
+
+    fn process_update_utilization(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        update_utilization_data: UpdateUtilizationData,
+    ) -> ProgramResult {
+        let UpdateUtilizationAccounts {
+            signer,
+            program_state_pda,
+            ..
+        } = UpdateUtilizationAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        assert_true(
+            update_utilization_data.utilization_target == 0 ||
+                (update_utilization_data.utilization_min_bps <= update_utilization_data.utilization_max_bps &&
+                update_utilization_data.utilization_max_bps > 0),
+            ProgramError::from(McPayError::InvalidUtilization),
+            "CERROR: Invalid utilization",
+        )?;
+
+        program_state.utilization_target = update_utilization_data.utilization_target;
+        program_state.utilization_min_bps = update_utilization_data.utilization_min_bps;
+        program_state.utilization_max_bps = update_utilization_data.utilization_max_bps;
+        program_state.store(program_state_pda)?;
+
+        Ok(())
+    }
+
+    fn process_create_season(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        create_season_data: CreateSeasonData,
+    ) -> ProgramResult {
+        let CreateSeasonAccounts {
+            signer,
+            program_state_pda,
+            season_pda,
+            system_program_id,
+            mcpay_vault_pickle_ata,
+            current_season_pda,
+            ..
+        } = CreateSeasonAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        assert_true(
+            create_season_data.season_id > 0 && create_season_data.start_utime < create_season_data.end_utime,
+            ProgramError::from(McPayError::InvalidSeason),
+            "CERROR: Invalid season",
+        )?;
+
+        // ids only grow, a closed season's records can never match a new season
+        assert_true(
+            create_season_data.season_id > program_state.current_season_id,
+            ProgramError::from(McPayError::InvalidSeason),
+            "CERROR: Season id must follow the current season",
+        )?;
+
+        // points only accrue to the current season, it has to end before another replaces it
+        if program_state.current_season_id > 0 {
+            let current_season_id_bytes = program_state.current_season_id.to_le_bytes();
+            let (current_season_state, _current_season_bump) = Self::load_or_find_pda::<SeasonState>(
+                program_id,
+                current_season_pda,
+                &[
+                    b"season",
+                    current_season_id_bytes.as_ref(),
+                ],
+                McPayError::InvalidSeasonPDA,
+            )?;
+
+            if let Some(current_season_state) = current_season_state {
+                assert_true(
+                    Clock::get()?.unix_timestamp > current_season_state.end_utime,
+                    ProgramError::from(McPayError::SeasonActive),
+                    "CERROR: Current season still active",
+                )?;
+            }
+        }
+
+        assert_true(
+            *mcpay_vault_pickle_ata.key == program_state.mcpay_vault_pickle_ata,
+            ProgramError::from(McPayError::InvalidMcPayVaultPickleATA),
+            "CERROR: Invalid mcpay vault pickle ata",
+        )?;
+
+        // the pool is reserved up front, so the vault must already hold it on top of what is reserved
+        let mcpay_vault_pickle_ata_data = unpack_token_account(mcpay_vault_pickle_ata)?;
+        assert_true(
+            Self::unreserved_chips(&program_state, mcpay_vault_pickle_ata_data.amount) >= create_season_data.bonus_pool,
+            ProgramError::from(McPayError::InsufficientVaultPickle),
+            "CERROR: Insufficient funds in Pickle Vault",
+        )?;
+
+        let season_id_bytes = create_season_data.season_id.to_le_bytes();
+        let (season, season_bump) = Pubkey::find_program_address(
+            &[
+                b"season",
+                season_id_bytes.as_ref(),
+            ],
+            program_id,
+        );
+        assert_true(
+            season == *season_pda.key,
+            ProgramError::from(McPayError::InvalidSeasonPDA),
+            "CERROR: Invalid season pda",
+        )?;
+
+        if season_pda.data_is_empty() {
+            msg!("Creating Season");
+            let season_size = SeasonState::LEN;
+            invoke_signed(
+                &system_instruction::create_account(
+                    signer.key,
+                    &season_pda.key,
+                    Rent::get()?.minimum_balance(season_size),
+                    season_size as u64,
+                    program_id,
+                ),
+                &[
+                    signer.clone(),
+                    season_pda.clone(),
+                    system_program_id.clone(),
+                ],
+                &[&[
+                    b"season",
+                    season_id_bytes.as_ref(),
+                    &[season_bump],
+                ]],
+            )?;
+
+            let mut season_state_data: SeasonState = SeasonState::init(season_pda, program_id)?;
+            season_state_data.is_initialized = true;
+            season_state_data.season_id = create_season_data.season_id;
+            season_state_data.start_utime = create_season_data.start_utime;
+            season_state_data.end_utime = create_season_data.end_utime;
+            season_state_data.bonus_pool = create_season_data.bonus_pool;
+            season_state_data.bump = season_bump;
+            season_state_data.store(season_pda)?;
+        } else {
+            msg!("CERROR: Season already exists");
+            return Err(McPayError::SeasonAlreadyExists.into());
+        }
+
+        // the pool stays reserved in the vault until it is distributed
+        program_state.total_season_pool_chips = program_state.total_season_pool_chips
+            .checked_add(create_season_data.bonus_pool)
+            .ok_or(McPayError::AmountOverflow)?;
+        program_state.current_season_id = create_season_data.season_id;
+        program_state.store(program_state_pda)?;
+
+        Ok(())
+    }
+
+    fn process_distribute_season_bonus(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        distribute_season_bonus_data: DistributeSeasonBonusData,
+    ) -> ProgramResult {
+        let DistributeSeasonBonusAccounts {
+            program_state_pda,
+            season_pda,
+            season_record_pda,
+            wallet,
+            wallet_pickle_ata,
+            spl_token_program_id,
+            mcpay_vault_pda,
+            mcpay_vault_pickle_ata,
+            ..
+        } = DistributeSeasonBonusAccounts::try_from(accounts)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        let season_id_bytes = distribute_season_bonus_data.season_id.to_le_bytes();
+        let (season, _season_bump) = Pubkey::find_program_address(
+            &[
+                b"season",
+                season_id_bytes.as_ref(),
+            ],
+            program_id,
+        );
+        assert_true(
+            season == *season_pda.key,
+            ProgramError::from(McPayError::InvalidSeasonPDA),
+            "CERROR: Invalid season pda",
+        )?;
+
+        let (season_record, _season_record_bump) = Pubkey::find_program_address(
+            &[
+                b"season-record",
+                season_id_bytes.as_ref(),
+                wallet.key.as_ref(),
+            ],
+            program_id,
+        );
+        assert_true(
+            season_record == *season_record_pda.key,
+            ProgramError::from(McPayError::InvalidSeasonRecordPDA),
+            "CERROR: Invalid season record pda",
+        )?;
+
+        assert_true(
+            *mcpay_vault_pda.key == program_state.mcpay_vault_pda,
+            ProgramError::from(McPayError::InvalidMcPayVaultPDA),
+            "CERROR: Invalid mcpay vault pda",
+        )?;
+        
+        assert_true(
+            *mcpay_vault_pickle_ata.key == program_state.mcpay_vault_pickle_ata,
+            ProgramError::from(McPayError::InvalidMcPayVaultPickleATA),
+            "CERROR: Invalid mcpay vault pickle ata",
+        )?;
+
+        let wallet_pickle = get_associated_token_address(
+            &wallet.key, 
+            &program_state.pickle_mint
+        );
+        assert_true(
+            *wallet_pickle_ata.key == wallet_pickle,
+            ProgramError::from(McPayError::InvalidATA),
+            "CERROR: Invalid wallet pickle ata",
+        )?;
+
+        let mut season_state_data: SeasonState = SeasonState::load(season_pda, program_id)?;
+        assert_true(
+            season_state_data.is_initialized,
+            ProgramError::from(McPayError::InvalidSeason),
+            "CERROR: Invalid season",
+        )?;
+
+        let clock = Clock::get()?;
+        assert_true(
+            clock.unix_timestamp > season_state_data.end_utime,
+            ProgramError::from(McPayError::SeasonNotEnded),
+            "CERROR: Season not ended",
+        )?;
+
+        assert_true(
+            !season_record_pda.data_is_empty(),
+            ProgramError::from(McPayError::NoSeasonPoints),
+            "CERROR: No season points",
+        )?;
+        let season_record_data: SeasonRecord = SeasonRecord::load(season_record_pda, program_id)?;
+        assert_true(
+            season_record_data.points > 0 && season_state_data.total_points > 0,
+            ProgramError::from(McPayError::NoSeasonPoints),
+            "CERROR: No season points",
+        )?;
+
+        let chips = (season_state_data.bonus_pool as u128)
+            .checked_mul(season_record_data.points as u128)
+            .ok_or(McPayError::AmountOverflow)?
+            / season_state_data.total_points as u128;
+        let chips = u64::try_from(chips).map_err(|_| McPayError::AmountOverflow)?;
+
+        // the season pools are reserved for this payout, everything else reserved stays untouched
+        let mcpay_vault_pickle_ata_data = unpack_token_account(mcpay_vault_pickle_ata)?;
+        assert_true(                    
+            Self::unreserved_chips(&program_state, mcpay_vault_pickle_ata_data.amount)
+                .saturating_add(program_state.total_season_pool_chips) >= chips,
+            ProgramError::from(McPayError::InsufficientVaultPickle),
+            "CERROR: Insufficient funds in Pickle Vault",
+        )?;
+
+        if chips > 0 {
+            msg!("Transferring Pickle");
+            let transfer_pickle_ix = spl_token::instruction::transfer(
+                spl_token_program_id.key,
+                mcpay_vault_pickle_ata.key,
+                wallet_pickle_ata.key,
+                &mcpay_vault_pda.key,
+                &[],
+                chips,
+            )?;
+            invoke_signed(
+                &transfer_pickle_ix,
+                &[
+                    mcpay_vault_pickle_ata.clone(),
+                    wallet_pickle_ata.clone(),
+                    mcpay_vault_pda.clone(),
+                ],
+                &[&[
+                    b"mcpay-vault",
+                    &[program_state.mcpay_vault_bump],
+                ]],
+            )?;
+        }
+
+        season_state_data.distributed_chips = season_state_data.distributed_chips
+            .checked_add(chips)
+            .ok_or(McPayError::AmountOverflow)?;
+        season_state_data.store(season_pda)?;
+
+        program_state.total_season_pool_chips = program_state.total_season_pool_chips.saturating_sub(chips);
+        program_state.store(program_state_pda)?;
+
+        msg!("Closing Season Record");
+        **wallet.try_borrow_mut_lamports()? = wallet
+            .lamports()
+            .checked_add(season_record_pda.lamports())
+            .ok_or(McPayError::AmountOverflow)?;
+        **season_record_pda.try_borrow_mut_lamports()? = 0;
+        *season_record_pda.try_borrow_mut_data()? = &mut [];
+
+        Ok(())
+    }
+
+    // releases what an ended season never paid out, rounding dust and undistributed shares,
+    // back to the unreserved balance, records left undistributed can no longer be paid
+    // and the season stays current, collecting nothing, until the next one is created
+    fn process_close_season(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        close_season_data: CloseSeasonData,
+    ) -> ProgramResult {
+        let CloseSeasonAccounts {
+            signer,
+            program_state_pda,
+            season_pda,
+            ..
+        } = CloseSeasonAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        let season_state_data: SeasonState = SeasonState::load(season_pda, program_id)?;
+        let season_id_bytes = close_season_data.season_id.to_le_bytes();
+        let (season, _season_bump) = Self::stored_bump_address(
+            &[
+                b"season",
+                season_id_bytes.as_ref(),
+            ],
+            season_state_data.bump,
+            program_id,
+            McPayError::InvalidSeasonPDA,
+        )?;
+        assert_true(
+            season == *season_pda.key,
+            ProgramError::from(McPayError::InvalidSeasonPDA),
+            "CERROR: Invalid season pda",
+        )?;
+
+        assert_true(
+            Clock::get()?.unix_timestamp > season_state_data.end_utime,
+            ProgramError::from(McPayError::SeasonNotEnded),
+            "CERROR: Season not ended",
+        )?;
+
+        let undistributed_chips = season_state_data.bonus_pool.saturating_sub(season_state_data.distributed_chips);
+        msg!("Releasing {} undistributed season chips", undistributed_chips);
+        program_state.total_season_pool_chips = program_state.total_season_pool_chips.saturating_sub(undistributed_chips);
+        program_state.store(program_state_pda)?;
+
+        msg!("Closing Season");
+        **signer.try_borrow_mut_lamports()? = signer
+            .lamports()
+            .checked_add(season_pda.lamports())
+            .ok_or(McPayError::AmountOverflow)?;
+        **season_pda.try_borrow_mut_lamports()? = 0;
+        *season_pda.try_borrow_mut_data()? = &mut [];
+
+        Ok(())
+    }
+
+    fn process_update_referral(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        update_referral_data: UpdateReferralData,
+    ) -> ProgramResult {
+        let UpdateReferralAccounts {
+            signer,
+            program_state_pda,
+            ..
+        } = UpdateReferralAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        assert_true(
+            update_referral_data.referral_bps <= 10_000,
+            ProgramError::from(McPayError::InvalidReferral),
+            "CERROR: Invalid referral",
+        )?;
+
+        program_state.referral_bps = update_referral_data.referral_bps;
+        program_state.store(program_state_pda)?;
+
+        Ok(())
+    }
+
+    fn process_claim_referral_rewards(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let ClaimReferralRewardsAccounts {
+            signer,
+            program_state_pda,
+            wallet_state_pda,
+            signer_pickle_ata,
+            spl_token_program_id,
+            mcpay_vault_pda,
+            mcpay_vault_pickle_ata,
+            ..
+        } = ClaimReferralRewardsAccounts::try_from(accounts)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        let (wallet_state_data, wallet_state_bump) = Self::load_or_find_pda::<WalletState>(
+            program_id,
+            wallet_state_pda,
+            &[
+                b"wallet-state",
+                signer.key.as_ref(),
+            ],
+            McPayError::InvalidWalletStatePDA,
+        )?;
+
+        assert_true(
+            *mcpay_vault_pda.key == program_state.mcpay_vault_pda,
+            ProgramError::from(McPayError::InvalidMcPayVaultPDA),
+            "CERROR: Invalid mcpay vault pda",
+        )?;
+        
+        assert_true(
+            *mcpay_vault_pickle_ata.key == program_state.mcpay_vault_pickle_ata,
+            ProgramError::from(McPayError::InvalidMcPayVaultPickleATA),
+            "CERROR: Invalid mcpay vault pickle ata",
+        )?;
+
+        let signer_pickle = get_associated_token_address(
+            &signer.key, 
+            &program_state.pickle_mint
+        );
+        assert_true(
+            *signer_pickle_ata.key == signer_pickle,
+            ProgramError::from(McPayError::InvalidATA),
+            "CERROR: Invalid signer pickle ata",
+        )?;
+
+        let Some(mut wallet_state_data) = wallet_state_data else {
+            msg!("CERROR: No referral rewards");
+            return Err(McPayError::NoReferralRewards.into());
+        };
+        wallet_state_data.bump = wallet_state_bump;
+        assert_true(
+            wallet_state_data.referral_chips_due > 0,
+            ProgramError::from(McPayError::NoReferralRewards),
+            "CERROR: No referral rewards",
+        )?;
+
+        // the referral rewards are reserved for this payout, everything else reserved stays untouched
+        let mcpay_vault_pickle_ata_data = unpack_token_account(mcpay_vault_pickle_ata)?;
+        assert_true(                    
+            Self::unreserved_chips(&program_state, mcpay_vault_pickle_ata_data.amount)
+                .saturating_add(program_state.total_referral_chips_due) >= wallet_state_data.referral_chips_due,
+            ProgramError::from(McPayError::InsufficientVaultPickle),
+            "CERROR: Insufficient funds in Pickle Vault",
+        )?;
+
+        msg!("Transferring Pickle");
+        let transfer_pickle_ix = spl_token::instruction::transfer(
+            spl_token_program_id.key,
+            mcpay_vault_pickle_ata.key,
+            signer_pickle_ata.key,
+            &mcpay_vault_pda.key,
+            &[],
+            wallet_state_data.referral_chips_due,
+        )?;
+        invoke_signed(
+            &transfer_pickle_ix,
+            &[
+                mcpay_vault_pickle_ata.clone(),
+                signer_pickle_ata.clone(),
+                mcpay_vault_pda.clone(),
+            ],
+            &[&[
+                b"mcpay-vault",
+                &[program_state.mcpay_vault_bump],
+            ]],
+        )?;
+
+        program_state.total_referral_chips_due = program_state.total_referral_chips_due
+            .saturating_sub(wallet_state_data.referral_chips_due);
+        program_state.store(program_state_pda)?;
+
+        wallet_state_data.referral_chips_due = 0;
+        wallet_state_data.store(wallet_state_pda)?;
+
+        Ok(())
+    }
+
+    fn process_update_clock_in_fees(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        update_clock_in_fees_data: UpdateClockInFeesData,
+    ) -> ProgramResult {
+        let UpdateClockInFeesAccounts {
+            signer,
+            program_state_pda,
+            ..
+        } = UpdateClockInFeesAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        assert_true(
+            update_clock_in_fees_data.fee_treasury_bps <= 10_000,
+            ProgramError::from(McPayError::InvalidClockInFees),
+            "CERROR: Invalid clock in fees",
+        )?;
+
+        // the vault pda and the treasury can start without lamports, a share below the rent
+        // exempt minimum would make the first fee transfer into either fail
+        let rent_lamports = Rent::get()?.minimum_balance(0);
+        for fee_lamports in [
+            update_clock_in_fees_data.level_one_fee,
+            update_clock_in_fees_data.level_seven_fee,
+            update_clock_in_fees_data.level_thirty_fee,
+        ] {
+            let treasury_lamports = Self::apply_bps(fee_lamports, update_clock_in_fees_data.fee_treasury_bps as u64)?;
+            let vault_lamports = fee_lamports - treasury_lamports;
+            assert_true(
+                vault_lamports == 0 || vault_lamports >= rent_lamports,
+                ProgramError::from(McPayError::InvalidClockInFees),
+                "CERROR: Vault share of the clock in fee is below the rent exempt minimum",
+            )?;
+            assert_true(
+                treasury_lamports == 0 || treasury_lamports >= rent_lamports,
+                ProgramError::from(McPayError::InvalidClockInFees),
+                "CERROR: Treasury share of the clock in fee is below the rent exempt minimum",
+            )?;
+        }
+
+        program_state.level_one_fee = update_clock_in_fees_data.level_one_fee;
+        program_state.level_seven_fee = update_clock_in_fees_data.level_seven_fee;
+        program_state.level_thirty_fee = update_clock_in_fees_data.level_thirty_fee;
+        program_state.fee_treasury_bps = update_clock_in_fees_data.fee_treasury_bps;
+        program_state.store(program_state_pda)?;
+
+        Ok(())
+    }
+
+    fn process_add_treasury(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        add_treasury_data: AddTreasuryData,
+    ) -> ProgramResult {
+        let AddTreasuryAccounts {
+            signer,
+            program_state_pda,
+            treasury_pda,
+            treasury_pickle_ata,
+            system_program_id,
+            ..
+        } = AddTreasuryAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        assert_true(
+            add_treasury_data.treasury_type > 0,
+            ProgramError::from(McPayError::InvalidTreasuryType),
+            "CERROR: Invalid treasury type",
+        )?;
+
+        let (treasury, treasury_bump) = Pubkey::find_program_address(
+            &[
+                b"treasury",
+                &[add_treasury_data.treasury_type],
+            ],
+            program_id,
+        );
+        assert_true(
+            treasury == *treasury_pda.key,
+            ProgramError::from(McPayError::InvalidTreasuryPDA),
+            "CERROR: Invalid treasury pda",
+        )?;
+
+        let treasury_pickle_ata_data = unpack_token_account(treasury_pickle_ata)?;
+        assert_true(
+            treasury_pickle_ata_data.mint == program_state.pickle_mint,
+            ProgramError::from(McPayError::InvalidTreasuryPickleATA),
+            "CERROR: Invalid treasury pickle ATA",
+        )?;
+
+        let mut treasury_state: TreasuryState = if treasury_pda.data_is_empty() {
+            msg!("Creating Treasury");
+            let treasury_size = TreasuryState::LEN;
+            invoke_signed(
+                &system_instruction::create_account(
+                    signer.key,
+                    &treasury_pda.key,
+                    Rent::get()?.minimum_balance(treasury_size),
+                    treasury_size as u64,
+                    program_id,
+                ),
+                &[
+                    signer.clone(),
+                    treasury_pda.clone(),
+                    system_program_id.clone(),
+                ],
+                &[&[
+                    b"treasury",
+                    &[add_treasury_data.treasury_type],
+                    &[treasury_bump],
+                ]],
+            )?;
+            TreasuryState::init(treasury_pda, program_id)?
+        } else {
+            TreasuryState::load(treasury_pda, program_id)?
+        };
+        treasury_state.is_initialized = true;
+        treasury_state.treasury_type = add_treasury_data.treasury_type;
+        treasury_state.name = add_treasury_data.name;
+        treasury_state.pickle_ata = *treasury_pickle_ata.key;
+        treasury_state.bump = treasury_bump;
+        treasury_state.store(treasury_pda)?;
+
+        Ok(())
+    }
+
+    fn process_remove_treasury(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        remove_treasury_data: RemoveTreasuryData,
+    ) -> ProgramResult {
+        let RemoveTreasuryAccounts {
+            signer,
+            treasury_pda,
+            ..
+        } = RemoveTreasuryAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let (treasury, _treasury_bump) = Pubkey::find_program_address(
+            &[
+                b"treasury",
+                &[remove_treasury_data.treasury_type],
+            ],
+            program_id,
+        );
+        assert_true(
+            treasury == *treasury_pda.key,
+            ProgramError::from(McPayError::InvalidTreasuryPDA),
+            "CERROR: Invalid treasury pda",
+        )?;
+
+        assert_true(
+            !treasury_pda.data_is_empty(),
+            ProgramError::from(McPayError::TreasuryNotFound),
+            "CERROR: Treasury not found",
+        )?;
+
+        msg!("Closing Treasury");
+        **signer.try_borrow_mut_lamports()? = signer
+            .lamports()
+            .checked_add(treasury_pda.lamports())
+            .ok_or(McPayError::AmountOverflow)?;
+        **treasury_pda.try_borrow_mut_lamports()? = 0;
+        *treasury_pda.try_borrow_mut_data()? = &mut [];
+
+        Ok(())
+    }
+
+    fn process_update_burn(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        update_burn_data: UpdateBurnData,
+    ) -> ProgramResult {
+        let UpdateBurnAccounts {
+            signer,
+            program_state_pda,
+            ..
+        } = UpdateBurnAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        assert_true(
+            update_burn_data.burn_bps <= 10_000,
+            ProgramError::from(McPayError::InvalidBurn),
+            "CERROR: Invalid burn",
+        )?;
+
+        program_state.burn_bps = update_burn_data.burn_bps;
+        program_state.store(program_state_pda)?;
+
+        Ok(())
+    }
+
+    fn process_update_vesting(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        update_vesting_data: UpdateVestingData,
+    ) -> ProgramResult {
+        let UpdateVestingAccounts {
+            signer,
+            program_state_pda,
+            ..
+        } = UpdateVestingAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        assert_true(
+            update_vesting_data.vesting_is_enabled < 2 && update_vesting_data.vesting_seconds >= 0,
+            ProgramError::from(McPayError::InvalidVesting),
+            "CERROR: Invalid vesting",
+        )?;
+
+        program_state.vesting_is_enabled = update_vesting_data.vesting_is_enabled;
+        program_state.vesting_seconds = update_vesting_data.vesting_seconds;
+        program_state.store(program_state_pda)?;
+
+        Ok(())
+    }
+
+    fn process_claim_vested(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let ClaimVestedAccounts {
+            signer,
+            program_state_pda,
+            vesting_pda,
+            signer_pickle_ata,
+            spl_token_program_id,
+            mcpay_vault_pda,
+            mcpay_vault_pickle_ata,
+            ..
+        } = ClaimVestedAccounts::try_from(accounts)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        let (vesting_state_data, vesting_bump) = Self::load_or_find_pda::<VestingState>(
+            program_id,
+            vesting_pda,
+            &[
+                b"vesting",
+                signer.key.as_ref(),
+            ],
+            McPayError::InvalidVestingPDA,
+        )?;
+
+        assert_true(
+            *mcpay_vault_pda.key == program_state.mcpay_vault_pda,
+            ProgramError::from(McPayError::InvalidMcPayVaultPDA),
+            "CERROR: Invalid mcpay vault pda",
+        )?;
+        
+        assert_true(
+            *mcpay_vault_pickle_ata.key == program_state.mcpay_vault_pickle_ata,
+            ProgramError::from(McPayError::InvalidMcPayVaultPickleATA),
+            "CERROR: Invalid mcpay vault pickle ata",
+        )?;
+
+        let signer_pickle = get_associated_token_address(
+            &signer.key, 
+            &program_state.pickle_mint
+        );
+        assert_true(
+            *signer_pickle_ata.key == signer_pickle,
+            ProgramError::from(McPayError::InvalidATA),
+            "CERROR: Invalid signer pickle ata",
+        )?;
+
+        let Some(mut vesting_state_data) = vesting_state_data else {
+            msg!("CERROR: Nothing vested");
+            return Err(McPayError::NothingVested.into());
+        };
+        vesting_state_data.bump = vesting_bump;
+
+        let clock = Clock::get()?;
+        let utime = clock.unix_timestamp;
+        Self::release_vested(&mut vesting_state_data, utime)?;
+        let claimable_chips = vesting_state_data.unlocked_chips;
+        assert_true(
+            claimable_chips > 0,
+            ProgramError::from(McPayError::NothingVested),
+            "CERROR: Nothing vested",
+        )?;
+
+        let mcpay_vault_pickle_ata_data = unpack_token_account(mcpay_vault_pickle_ata)?;
+        assert_true(                    
+            mcpay_vault_pickle_ata_data.amount >= claimable_chips,
+            ProgramError::from(McPayError::InsufficientVaultPickle),
+            "CERROR: Insufficient funds in Pickle Vault",
+        )?;
+
+        msg!("Transferring Pickle");
+        let transfer_pickle_ix = spl_token::instruction::transfer(
+            spl_token_program_id.key,
+            mcpay_vault_pickle_ata.key,
+            signer_pickle_ata.key,
+            &mcpay_vault_pda.key,
+            &[],
+            claimable_chips,
+        )?;
+        invoke_signed(
+            &transfer_pickle_ix,
+            &[
+                mcpay_vault_pickle_ata.clone(),
+                signer_pickle_ata.clone(),
+                mcpay_vault_pda.clone(),
+            ],
+            &[&[
+                b"mcpay-vault",
+                &[program_state.mcpay_vault_bump],
+            ]],
+        )?;
+
+        program_state.total_vesting_chips = program_state.total_vesting_chips.saturating_sub(claimable_chips);
+        program_state.store(program_state_pda)?;
+
+        vesting_state_data.unlocked_chips = 0;
+        if vesting_state_data.tranches.iter().all(|tranche| tranche.locked_chips == 0) {
+            msg!("Closing Vesting State");
+            **signer.try_borrow_mut_lamports()? = signer
+                .lamports()
+                .checked_add(vesting_pda.lamports())
+                .ok_or(McPayError::AmountOverflow)?;
+            **vesting_pda.try_borrow_mut_lamports()? = 0;
+            *vesting_pda.try_borrow_mut_data()? = &mut [];
+        } else {
+            vesting_state_data.store(vesting_pda)?;
+        }
+
+        Ok(())
+    }
+
+    fn process_set_asset_flag(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        set_asset_flag_data: SetAssetFlagData,
+    ) -> ProgramResult {
+        let SetAssetFlagAccounts {
+            signer,
+            asset_flag_pda,
+            system_program_id,
+            ..
+        } = SetAssetFlagAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let (asset_flag, asset_flag_bump) = Pubkey::find_program_address(
+            &[
+                b"asset-flag",
+                set_asset_flag_data.asset_id.as_ref(),
+            ],
+            program_id,
+        );
+        assert_true(
+            asset_flag == *asset_flag_pda.key,
+            ProgramError::from(McPayError::InvalidAssetFlagPDA),
+            "CERROR: Invalid asset flag pda",
+        )?;
+
+        // flag 0 clears the asset
+        if set_asset_flag_data.flag == 0 {
+            if !asset_flag_pda.data_is_empty() {
+                msg!("Closing Asset Flag");
+                **signer.try_borrow_mut_lamports()? = signer
+                    .lamports()
+                    .checked_add(asset_flag_pda.lamports())
+                    .ok_or(McPayError::AmountOverflow)?;
+                **asset_flag_pda.try_borrow_mut_lamports()? = 0;
+                *asset_flag_pda.try_borrow_mut_data()? = &mut [];
+            }
+            return Ok(());
+        }
+
+        assert_true(
+            set_asset_flag_data.flag == ASSET_FLAG_DENY || set_asset_flag_data.flag == ASSET_FLAG_ALLOW,
+            ProgramError::from(McPayError::InvalidAssetFlag),
+            "CERROR: Invalid asset flag",
+        )?;
+
+        let mut asset_flag_data: AssetFlag = if asset_flag_pda.data_is_empty() {
+            msg!("Creating Asset Flag");
+            let asset_flag_size = AssetFlag::LEN;
+            invoke_signed(
+                &system_instruction::create_account(
+                    signer.key,
+                    &asset_flag_pda.key,
+                    Rent::get()?.minimum_balance(asset_flag_size),
+                    asset_flag_size as u64,
+                    program_id,
+                ),
+                &[
+                    signer.clone(),
+                    asset_flag_pda.clone(),
+                    system_program_id.clone(),
+                ],
+                &[&[
+                    b"asset-flag",
+                    set_asset_flag_data.asset_id.as_ref(),
+                    &[asset_flag_bump],
+                ]],
+            )?;
+            AssetFlag::init(asset_flag_pda, program_id)?
+        } else {
+            AssetFlag::load(asset_flag_pda, program_id)?
+        };
+        asset_flag_data.is_initialized = true;
+        asset_flag_data.asset_id = set_asset_flag_data.asset_id;
+        asset_flag_data.flag = set_asset_flag_data.flag;
+        asset_flag_data.bump = asset_flag_bump;
+        asset_flag_data.store(asset_flag_pda)?;
+
+        Ok(())
+    }
+
+    fn process_update_allowlist(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        update_allowlist_data: UpdateAllowlistData,
+    ) -> ProgramResult {
+        let UpdateAllowlistAccounts {
+            signer,
+            program_state_pda,
+            ..
+        } = UpdateAllowlistAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        assert_true(
+            update_allowlist_data.allowlist_only < 2,
+            ProgramError::from(McPayError::InvalidAllowlist),
+            "CERROR: Invalid allowlist",
+        )?;
+
+        program_state.allowlist_only = update_allowlist_data.allowlist_only;
+        program_state.store(program_state_pda)?;
+
+        Ok(())
+    }
+
+    fn process_force_close_asset_state(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        force_close_asset_state_data: ForceCloseAssetStateData,
+    ) -> ProgramResult {
+        let ForceCloseAssetStateAccounts {
+            signer,
+            program_state_pda,
+            asset_state_pda,
+            clock_in_wallet,
+            wallet_state_pda,
+            spl_token_program_id,
+            mcpay_vault_pda,
+            mcpay_vault_pickle_ata,
+            clock_in_wallet_pickle_ata,
+            rent_payer,
+            ..
+        } = ForceCloseAssetStateAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        assert_true(
+            force_close_asset_state_data.pay_chips_due < 2,
+            ProgramError::from(McPayError::InvalidInstructionData),
+            "CERROR: Invalid pay chips due",
+        )?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        assert_true(
+            !asset_state_pda.data_is_empty(),
+            ProgramError::from(McPayError::NotClockedIn),
+            "CERROR: Asset not clocked in",
+        )?;
+        let asset_state_data: AssetState = AssetState::load(asset_state_pda, program_id)?;
+
+        let (asset_state, _asset_state_bump) = Self::stored_bump_address(
+            &[
+                b"asset-state",
+                asset_state_data.asset_id.as_ref(),
+            ],
+            asset_state_data.bump,
+            program_id,
+            McPayError::InvalidAssetStatePDA,
+        )?;
+        assert_true(
+            asset_state == *asset_state_pda.key,
+            ProgramError::from(McPayError::InvalidAssetStatePDA),
+            "CERROR: Invalid asset state pda",
+        )?;
+
+        // core and legacy assets stay frozen with the vault as delegate until their owner clocks them out,
+        // which a denied asset can still do without payout
+        assert_true(
+            asset_state_data.asset_kind == ASSET_KIND_COMPRESSED,
+            ProgramError::from(McPayError::AssetFrozen),
+            "CERROR: Asset is frozen, clock it out to thaw it",
+        )?;
+
+        assert_true(
+            *clock_in_wallet.key == asset_state_data.clock_in_wallet,
+            ProgramError::from(McPayError::InvalidClockInWallet),
+            "CERROR: Invalid clock in wallet",
+        )?;
+
+        assert_true(
+            *rent_payer.key == Self::asset_rent_payer(&asset_state_data),
+            ProgramError::from(McPayError::InvalidRentPayer),
+            "CERROR: Invalid rent payer",
+        )?;
+
+        let (wallet_state_data, wallet_state_bump) = Self::load_or_find_pda::<WalletState>(
+            program_id,
+            wallet_state_pda,
+            &[
+                b"wallet-state",
+                clock_in_wallet.key.as_ref(),
+            ],
+            McPayError::InvalidWalletStatePDA,
+        )?;
+
+        // a refund of the recorded chips_due to the clock in wallet, outside the clock out rules on purpose:
+        // no leaf is proven here, so there is no owner for the payout policy, and nothing is burned or vested
+        if force_close_asset_state_data.pay_chips_due == 1 {
+            assert_true(
+                *mcpay_vault_pda.key == program_state.mcpay_vault_pda,
+                ProgramError::from(McPayError::InvalidMcPayVaultPDA),
+                "CERROR: Invalid mcpay vault pda",
+            )?;
+
+            assert_true(
+                *mcpay_vault_pickle_ata.key == program_state.mcpay_vault_pickle_ata,
+                ProgramError::from(McPayError::InvalidMcPayVaultPickleATA),
+                "CERROR: Invalid mcpay vault pickle ata",
+            )?;
+
+            let clock_in_wallet_pickle = get_associated_token_address(
+                &clock_in_wallet.key, 
+                &program_state.pickle_mint
+            );
+            assert_true(
+                *clock_in_wallet_pickle_ata.key == clock_in_wallet_pickle,
+                ProgramError::from(McPayError::InvalidATA),
+                "CERROR: Invalid clock in wallet pickle ata",
+            )?;
+
+            let mcpay_vault_pickle_ata_data = unpack_token_account(mcpay_vault_pickle_ata)?;
+            assert_true(                    
+                Self::unreserved_chips(&program_state, mcpay_vault_pickle_ata_data.amount) >= asset_state_data.chips_due,
+                ProgramError::from(McPayError::InsufficientVaultPickle),
+                "CERROR: Insufficient funds in Pickle Vault",
+            )?;
+
+            msg!("Transferring Pickle");
+            let transfer_pickle_ix = spl_token::instruction::transfer(
+                spl_token_program_id.key,
+                mcpay_vault_pickle_ata.key,
+                clock_in_wallet_pickle_ata.key,
+                &mcpay_vault_pda.key,
+                &[],
+                asset_state_data.chips_due,
+            )?;
+            invoke_signed(
+                &transfer_pickle_ix,
+                &[
+                    mcpay_vault_pickle_ata.clone(),
+                    clock_in_wallet_pickle_ata.clone(),
+                    mcpay_vault_pda.clone(),
+                ],
+                &[&[
+                    b"mcpay-vault",
+                    &[program_state.mcpay_vault_bump],
+                ]],
+            )?;
+        }
+
+        if let Some(mut wallet_state_data) = wallet_state_data {
+            wallet_state_data.assets_clocked_in = wallet_state_data.assets_clocked_in.saturating_sub(1);
+            wallet_state_data.bump = wallet_state_bump;
+            wallet_state_data.store(wallet_state_pda)?;
+        }
+
+        program_state.assets_clocked_in = program_state.assets_clocked_in.saturating_sub(1);
+        program_state.store(program_state_pda)?;
+
+        msg!(
+            "Force closed asset {} reason {} chips due {} paid {}",
+            asset_state_data.asset_id,
+            force_close_asset_state_data.reason_code,
+            asset_state_data.chips_due,
+            force_close_asset_state_data.pay_chips_due == 1,
+        );
+
+        msg!("Closing Asset State");
+        **rent_payer.try_borrow_mut_lamports()? = rent_payer
+            .lamports()
+            .checked_add(asset_state_pda.lamports())
+            .ok_or(McPayError::AmountOverflow)?;
+        **asset_state_pda.try_borrow_mut_lamports()? = 0;
+        *asset_state_pda.try_borrow_mut_data()? = &mut [];
+
+        Ok(())
+    }
+
+    fn process_update_payout_policy(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        update_payout_policy_data: UpdatePayoutPolicyData,
+    ) -> ProgramResult {
+        let UpdatePayoutPolicyAccounts {
+            signer,
+            program_state_pda,
+            ..
+        } = UpdatePayoutPolicyAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        assert_true(
+            update_payout_policy_data.payout_policy <= PAYOUT_RECIPIENT,
+            ProgramError::from(McPayError::InvalidPayoutPolicy),
+            "CERROR: Invalid payout policy",
+        )?;
+
+        program_state.payout_policy = update_payout_policy_data.payout_policy;
+        program_state.store(program_state_pda)?;
+
+        Ok(())
+    }
+
+    fn process_update_core_collection(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        update_core_collection_data: UpdateCoreCollectionData,
+    ) -> ProgramResult {
+        let UpdateCoreCollectionAccounts {
+            signer,
+            program_state_pda,
+            ..
+        } = UpdateCoreCollectionAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        program_state.core_collection = update_core_collection_data.core_collection;
+        program_state.store(program_state_pda)?;
+
+        Ok(())
+    }
+
+    fn process_update_legacy_collection(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        update_legacy_collection_data: UpdateLegacyCollectionData,
+    ) -> ProgramResult {
+        let UpdateLegacyCollectionAccounts {
+            signer,
+            program_state_pda,
+            ..
+        } = UpdateLegacyCollectionAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        program_state.legacy_collection = update_legacy_collection_data.legacy_collection;
+        program_state.store(program_state_pda)?;
+
+        Ok(())
+    }
+
+    fn process_update_merkle_tree_v2(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        update_merkle_tree_v2_data: UpdateMerkleTreeV2Data,
+    ) -> ProgramResult {
+        let UpdateMerkleTreeV2Accounts {
+            signer,
+            program_state_pda,
+            ..
+        } = UpdateMerkleTreeV2Accounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        program_state.merkle_tree_v2 = update_merkle_tree_v2_data.merkle_tree_v2;
+        program_state.store(program_state_pda)?;
+
+        Ok(())
+    }
+
+    // upgrades an account written before the account header, or under an older layout version, in place
+    fn process_migrate_state(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        migrate_state_data: MigrateStateData,
+    ) -> ProgramResult {
+        let MigrateStateAccounts {
+            signer,
+            state_account,
+            system_program_id,
+            ..
+        } = MigrateStateAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        assert_owner(state_account, program_id, McPayError::InvalidAccountOwner)?;
+        assert_true(
+            !state_account.data_is_empty(),
+            ProgramError::from(McPayError::InvalidAccountDiscriminator),
+            "CERROR: Account has no data to migrate",
+        )?;
+
+        match migrate_state_data.account_type {
+            ACCOUNT_TYPE_PROGRAM_STATE => Self::migrate_account::<ProgramState>(program_id, signer, state_account, system_program_id),
+            ACCOUNT_TYPE_ASSET_STATE => Self::migrate_account::<AssetState>(program_id, signer, state_account, system_program_id),
+            ACCOUNT_TYPE_WALLET_STATE => Self::migrate_account::<WalletState>(program_id, signer, state_account, system_program_id),
+            ACCOUNT_TYPE_ASSET_FLAG => Self::migrate_account::<AssetFlag>(program_id, signer, state_account, system_program_id),
+            ACCOUNT_TYPE_VESTING_STATE => Self::migrate_account::<VestingState>(program_id, signer, state_account, system_program_id),
+            ACCOUNT_TYPE_TREASURY_STATE => Self::migrate_account::<TreasuryState>(program_id, signer, state_account, system_program_id),
+            ACCOUNT_TYPE_SEASON_STATE => Self::migrate_account::<SeasonState>(program_id, signer, state_account, system_program_id),
+            ACCOUNT_TYPE_SEASON_RECORD => Self::migrate_account::<SeasonRecord>(program_id, signer, state_account, system_program_id),
+            _ => {
+                msg!("CERROR: Invalid account type");
+                Err(McPayError::InvalidAccountType.into())
+            },
+        }
+    }
+
+    // assets clocked in before the count existed are missing from it, the admin sets it from an off chain tally
+    fn process_update_assets_clocked_in(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        update_assets_clocked_in_data: UpdateAssetsClockedInData,
+    ) -> ProgramResult {
+        let UpdateAssetsClockedInAccounts {
+            signer,
+            program_state_pda,
+            ..
+        } = UpdateAssetsClockedInAccounts::try_from(accounts)?;
+
+        assert_admin(signer)?;
+
+        let mut program_state: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        if !program_state.is_initialized {
+            msg!("CERROR: Program state not initialized");
+            return Err(McPayError::ProgramStateNotInitialized.into());            
+        }
+
+        msg!("Assets clocked in {} -> {}", program_state.assets_clocked_in, update_assets_clocked_in_data.assets_clocked_in);
+        program_state.assets_clocked_in = update_assets_clocked_in_data.assets_clocked_in;
+        program_state.store(program_state_pda)?;
+
+        Ok(())
+    }
+
+    // program state checked against the address derived from its stored bump
+    fn load_program_state(
+        program_id: &Pubkey,
+        program_state_pda: &AccountInfo,
+    ) -> Result<ProgramState, ProgramError> {
+        let program_state_data: ProgramState = ProgramState::load(program_state_pda, program_id)?;
+
+        let (program_state, _program_state_bump) = Self::stored_bump_address(
+            &[b"program-state"],
+            program_state_data.program_state_bump,
+            program_id,
+            McPayError::InvalidProgramStatePDA,
+        )?;
+        assert_true(
+            program_state == *program_state_pda.key,
+            ProgramError::from(McPayError::InvalidProgramStatePDA),
+            "CERROR: Invalid program state pda",
+        )?;
+
+        Ok(program_state_data)
+    }
+
+    // create_program_address with a stored bump skips the find_program_address search,
+    // a bump of 0 was written before bumps were stored and falls back to the search
+    fn stored_bump_address(
+        seeds: &[&[u8]],
+        bump: u8,
+        program_id: &Pubkey,
+        error: McPayError,
+    ) -> Result<(Pubkey, u8), ProgramError> {
+        if bump == 0 {
+            return Ok(Pubkey::find_program_address(seeds, program_id));
+        }
+
+        let bump_seed = [bump];
+        let mut bump_seeds = seeds.to_vec();
+        bump_seeds.push(&bump_seed);
+        let address = Pubkey::create_program_address(&bump_seeds, program_id).map_err(|_| ProgramError::from(error))?;
+        Ok((address, bump))
+    }
+
+    // a bump passed in by the client, only the canonical one is accepted or the same seeds
+    // could open a second account, 0 falls back to the search
+    fn canonical_bump_address(
+        seeds: &[&[u8]],
+        bump: u8,
+        program_id: &Pubkey,
+        error: McPayError,
+    ) -> Result<(Pubkey, u8), ProgramError> {
+        if bump == 0 {
+            return Ok(Pubkey::find_program_address(seeds, program_id));
+        }
+
+        let mut higher_bump = bump;
+        while higher_bump < u8::MAX {
+            higher_bump += 1;
+            let bump_seed = [higher_bump];
+            let mut bump_seeds = seeds.to_vec();
+            bump_seeds.push(&bump_seed);
+            if Pubkey::create_program_address(&bump_seeds, program_id).is_ok() {
+                msg!("CERROR: Bump {} is not canonical", bump);
+                return Err(error.into());
+            }
+        }
+
+        Self::stored_bump_address(seeds, bump, program_id, error)
+    }
+
+    // an account the program may still have to create, a loaded one is checked with the bump
+    // it stores and an empty one is found, the bump is returned for creating or recording it
+    fn load_or_find_pda<T: AccountState>(
+        program_id: &Pubkey,
+        account: &AccountInfo,
+        seeds: &[&[u8]],
+        error: McPayError,
+    ) -> Result<(Option<T>, u8), ProgramError> {
+        let (state, stored_bump) = if account.data_is_empty() {
+            (None, 0)
+        } else {
+            let state = T::load(account, program_id)?;
+            let stored_bump = state.bump();
+            (Some(state), stored_bump)
+        };
+
+        let (address, bump) = Self::stored_bump_address(seeds, stored_bump, program_id, error)?;
+        assert_address(account, &address, error)?;
+
+        Ok((state, bump))
+    }
+
+    // checks shared by every clock in, returns the program state to update
+    fn clock_in_program_state<'a>(
+        program_id: &Pubkey,
+        program_state_pda: &AccountInfo<'a>,
+        mcpay_vault_pda: &AccountInfo<'a>,
+        mcdegens_treasury: &AccountInfo<'a>,
+    ) -> Result<ProgramState, ProgramError> {
+        let program_state_data: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        assert_true(
+            program_state_data.is_initialized,
+            ProgramError::from(McPayError::ProgramStateNotInitialized),
+            "CERROR: Program state not initialized",
+        )?;
+
+        assert_true(
+            program_state_data.clock_in_is_enabled == 1,
+            ProgramError::from(McPayError::ClockInDisabled),
+            "CERROR: Clock in disabled",
+        )?;
+
+        assert_true(
+            *mcpay_vault_pda.key == program_state_data.mcpay_vault_pda,
+            ProgramError::from(McPayError::InvalidMcPayVaultPDA),
+            "CERROR: Invalid mcpay vault pda",
+        )?;
+
+        assert_true(
+            *mcdegens_treasury.key == program_state_data.mcdegens_treasury,
+            ProgramError::from(McPayError::InvalidMcDegensTreasury),
+            "CERROR: Invalid McDegens Treasury",
+        )?;
+
+        Ok(program_state_data)
+    }
+
+    // signer's wallet state, created with the referrer on first clock in
+    fn clock_in_wallet_state<'a>(
+        program_id: &Pubkey,
+        wallet: &Pubkey,
+        payer: &AccountInfo<'a>,
+        wallet_state_pda: &AccountInfo<'a>,
+        referrer_wallet_state_pda: &AccountInfo<'a>,
+        system_program_id: &AccountInfo<'a>,
+        referrer: &Pubkey,
+    ) -> Result<WalletState, ProgramError> {
+        let (wallet_state_data, wallet_state_bump) = Self::load_or_find_pda::<WalletState>(
+            program_id,
+            wallet_state_pda,
+            &[
+                b"wallet-state",
+                wallet.as_ref(),
+            ],
+            McPayError::InvalidWalletStatePDA,
+        )?;
+
+        let mut wallet_state_data: WalletState = if let Some(wallet_state_data) = wallet_state_data {
+            wallet_state_data
+        } else {
+            msg!("Creating Wallet State");
+            let wallet_state_size = WalletState::LEN;
+            invoke_signed(
+                &system_instruction::create_account(
+                    payer.key,
+                    &wallet_state_pda.key,
+                    Rent::get()?.minimum_balance(wallet_state_size),
+                    wallet_state_size as u64,
+                    program_id,
+                ),
+                &[
+                    payer.clone(),
+                    wallet_state_pda.clone(),
+                    system_program_id.clone(),
+                ],
+                &[&[
+                    b"wallet-state",
+                    wallet.as_ref(),
+                    &[wallet_state_bump],
+                ]],
+            )?;
+            WalletState::init(wallet_state_pda, program_id)?
+        };
+        wallet_state_data.bump = wallet_state_bump;
+        if !wallet_state_data.is_initialized {
+            wallet_state_data.is_initialized = true;
+            wallet_state_data.wallet = *wallet;
+
+            let no_referrer = Pubkey::default();
+            if *referrer != no_referrer {
+                let (referrer_wallet_state_data, _referrer_wallet_state_bump) = Self::load_or_find_pda::<WalletState>(
+                    program_id,
+                    referrer_wallet_state_pda,
+                    &[
+                        b"wallet-state",
+                        referrer.as_ref(),
+                    ],
+                    McPayError::InvalidWalletStatePDA,
+                )?;
+
+                // only wallets that have clocked in before can refer
+                assert_true(
+                    *referrer != *wallet && referrer_wallet_state_data.is_some(),
+                    ProgramError::from(McPayError::InvalidReferrer),
+                    "CERROR: Invalid referrer",
+                )?;
+
+                msg!("Recording referrer {}", referrer);
+                wallet_state_data.referrer = *referrer;
+            }
+        }
+
+        Ok(wallet_state_data)
+    }
+
+    // creates the asset state for an already verified asset, returns the clock in fee owed for it
+    #[allow(clippy::too_many_arguments)]
+    fn clock_in_asset<'a>(
+        program_id: &Pubkey,
+        program_state_data: &mut ProgramState,
+        wallet_state_data: &mut WalletState,
+        clock_in_wallet: &Pubkey,
+        rent_payer: &AccountInfo<'a>,
+        system_program_id: &AccountInfo<'a>,
+        asset_state_pda: &AccountInfo<'a>,
+        asset_flag_pda: &AccountInfo<'a>,
+        asset_id: Pubkey,
+        asset_kind: u8,
+        asset_state_bump: u8,
+        asset_flag_bump: u8,
+        level: u8,
+        payout_recipient: &Pubkey,
+        utime: i64,
+    ) -> Result<u64, ProgramError> {
+        let (asset_state, asset_state_bump) = Self::canonical_bump_address(
+            &[
+                b"asset-state",
+                asset_id.as_ref(),
+            ],
+            asset_state_bump,
+            program_id,
+            McPayError::InvalidAssetStatePDA,
+        )?;
+        assert_true(
+            asset_state == *asset_state_pda.key,
+            ProgramError::from(McPayError::InvalidAssetStatePDA),
+            "CERROR: Invalid asset state pda",
+        )?;
+
+        let (asset_flag, asset_flag_bump) = Self::asset_flag(program_id, &asset_id, asset_flag_pda, asset_flag_bump, false)?;
+        assert_true(
+            asset_flag != ASSET_FLAG_DENY,
+            ProgramError::from(McPayError::AssetDenied),
+            "CERROR: Asset denied",
+        )?;
+
+        assert_true(
+            program_state_data.allowlist_only == 0 || asset_flag == ASSET_FLAG_ALLOW,
+            ProgramError::from(McPayError::AssetNotAllowed),
+            "CERROR: Asset not allowed",
+        )?;
+
+        let mut clock_out_utime = utime;
+        let mut chips_due = 0;
+        let mut fee_lamports = 0;
+        if level == 1 {
+            clock_out_utime += 86_400;
+            chips_due = program_state_data.level_one_rate;
+            fee_lamports = program_state_data.level_one_fee;
+        } else if level == 7 {
+            clock_out_utime += 86_400 * level as i64;
+            chips_due = program_state_data.level_seven_rate;
+            fee_lamports = program_state_data.level_seven_fee;
+        } else if level == 30 {
+            clock_out_utime += 86_400 * level as i64;
+            chips_due = program_state_data.level_thirty_rate;
+            fee_lamports = program_state_data.level_thirty_fee;
+        }
+
+        chips_due = Self::scheduled_rate(
+            program_state_data,
+            chips_due,
+            level,
+            utime,
+        );
+
+        if !asset_state_pda.data_is_empty() {
+            msg!("CERROR: Asset already clocked in");
+            return Err(McPayError::AlreadyClockedIn.into());
+        }
+
+        wallet_state_data.assets_clocked_in = wallet_state_data.assets_clocked_in
+            .checked_add(1)
+            .ok_or(McPayError::AmountOverflow)?;
+
+        program_state_data.assets_clocked_in = program_state_data.assets_clocked_in
+            .checked_add(1)
+            .ok_or(McPayError::AmountOverflow)?;
+        if program_state_data.utilization_target > 0 {
+            let utilization_bps = Self::utilization_bps(program_state_data);
+            msg!("Utilization rate {} bps", utilization_bps);
+            chips_due = Self::apply_bps(chips_due, utilization_bps as u64)?;
+        }
+
+        let bonus_bps = Self::wallet_bonus_bps(
+            &program_state_data.bonus_brackets,
+            wallet_state_data.assets_clocked_in,
+        );
+        if bonus_bps > 0 {
+            msg!("Wallet bonus {} bps", bonus_bps);
+            chips_due = Self::apply_bps(chips_due, 10_000 + bonus_bps as u64)?;
+        }
+
+        // checked after the schedule and bonuses, a decayed or scaled rate can still round to 0
+        assert_true(
+            chips_due > 0,
+            ProgramError::from(McPayError::InvalidLevel),
+            "CERROR: Invalid level",
+        )?;
+
+        if program_state_data.emission_cap > 0 {
+            let emission_cap_epoch_end_utime = program_state_data.emission_cap_epoch_start_utime
+                .checked_add(program_state_data.emission_cap_epoch_seconds)
+                .ok_or(McPayError::AmountOverflow)?;
+            if program_state_data.emission_cap_epoch_seconds > 0 && utime >= emission_cap_epoch_end_utime {
+                msg!("Starting Emission Cap Epoch");
+                program_state_data.emission_cap_epoch_start_utime = utime - utime % program_state_data.emission_cap_epoch_seconds;
+                program_state_data.emission_committed = 0;
+            }
+            let emission_committed = program_state_data.emission_committed
+                .checked_add(chips_due)
+                .ok_or(McPayError::AmountOverflow)?;
+            assert_true(
+                emission_committed <= program_state_data.emission_cap,
+                ProgramError::from(McPayError::EmissionCapReached),
+                "CERROR: Emission cap reached",
+            )?;
+            program_state_data.emission_committed = emission_committed;
+        }
+
+        msg!("Creating Asset State");
+        let asset_state_size = AssetState::LEN;
+        invoke_signed(
+            &system_instruction::create_account(
+                rent_payer.key,
+                &asset_state_pda.key,
+                Rent::get()?.minimum_balance(asset_state_size),
+                asset_state_size as u64,
+                program_id,
+            ),
+            &[
+                rent_payer.clone(),
+                asset_state_pda.clone(),
+                system_program_id.clone(),
+            ],
+            &[&[
+                b"asset-state",
+                asset_id.as_ref(),
+                &[asset_state_bump],
+            ]],
+        )?;
+
+        let mut asset_state_data: AssetState = AssetState::init(asset_state_pda, program_id)?;
+        asset_state_data.is_initialized = true;
+        asset_state_data.clock_in_wallet = *clock_in_wallet;
+        asset_state_data.asset_id = asset_id;
+        asset_state_data.clock_in_utime = utime;
+        asset_state_data.clock_out_utime = clock_out_utime;
+        asset_state_data.level = level;
+        asset_state_data.chips_due = chips_due;
+        asset_state_data.payout_recipient = *payout_recipient;
+        asset_state_data.rent_payer = *rent_payer.key;
+        asset_state_data.asset_kind = asset_kind;
+        asset_state_data.bump = asset_state_bump;
+        asset_state_data.asset_flag_bump = asset_flag_bump;
+        asset_state_data.store(asset_state_pda)?;
+
+        Ok(fee_lamports)
+    }
+
+    // owner of a Core asset in the configured collection
+    fn core_asset_owner(
+        program_state_data: &ProgramState,
+        asset: &AccountInfo,
+        collection: &AccountInfo,
+    ) -> Result<Pubkey, ProgramError> {
+        let no_collection = Pubkey::default();
+        assert_true(
+            program_state_data.core_collection != no_collection && *collection.key == program_state_data.core_collection,
+            ProgramError::from(McPayError::InvalidCoreCollection),
+            "CERROR: Invalid core collection",
+        )?;
+
+        let asset_data = mpl_core::accounts::BaseAssetV1::from_bytes(&asset.data.borrow())
+            .map_err(|_| McPayError::InvalidCoreAsset)?;
+
+        assert_true(
+            asset_data.update_authority == mpl_core::types::UpdateAuthority::Collection(program_state_data.core_collection),
+            ProgramError::from(McPayError::InvalidCoreCollection),
+            "CERROR: Asset not in core collection",
+        )?;
+
+        Ok(asset_data.owner)
+    }
+
+    // owner of a legacy Token Metadata nft in the configured collection
+    fn legacy_asset_owner(
+        program_state_data: &ProgramState,
+        mint: &AccountInfo,
+        token_account: &AccountInfo,
+        metadata_pda: &AccountInfo,
+        edition_pda: &AccountInfo,
+    ) -> Result<Pubkey, ProgramError> {
+        let token_account_data = unpack_token_account(token_account)?;
+        assert_true(
+            token_account_data.mint == *mint.key && token_account_data.amount == 1,
+            ProgramError::from(McPayError::InvalidTokenAccount),
+            "CERROR: Invalid token account",
+        )?;
+
+        let (metadata, _metadata_bump) = mpl_token_metadata::accounts::Metadata::find_pda(mint.key);
+        assert_true(
+            metadata == *metadata_pda.key && *metadata_pda.owner == mpl_token_metadata::ID,
+            ProgramError::from(McPayError::InvalidMetadataPDA),
+            "CERROR: Invalid metadata pda",
+        )?;
+
+        let (edition, _edition_bump) = mpl_token_metadata::accounts::MasterEdition::find_pda(mint.key);
+        assert_true(
+            edition == *edition_pda.key,
+            ProgramError::from(McPayError::InvalidEditionPDA),
+            "CERROR: Invalid edition pda",
+        )?;
+
+        let metadata_data = mpl_token_metadata::accounts::Metadata::from_bytes(&metadata_pda.data.borrow())
+            .map_err(|_| McPayError::InvalidMetadataPDA)?;
+
+        // programmable nfts can't be frozen through a plain token delegate
+        assert_true(
+            metadata_data.token_standard != Some(mpl_token_metadata::types::TokenStandard::ProgrammableNonFungible),
+            ProgramError::from(McPayError::InvalidTokenAccount),
+            "CERROR: Programmable nfts not supported",
+        )?;
+
+        let no_collection = Pubkey::default();
+        let in_collection = match metadata_data.collection {
+            Some(collection) => collection.verified && collection.key == program_state_data.legacy_collection,
+            None => false,
+        };
+        assert_true(
+            program_state_data.legacy_collection != no_collection && in_collection,
+            ProgramError::from(McPayError::InvalidLegacyCollection),
+            "CERROR: Asset not in legacy collection",
+        )?;
+
+        Ok(token_account_data.owner)
+    }
+
+    // the compressed asset paths only accept the configured tree
+    fn check_merkle_tree(
+        program_state_data: &ProgramState,
+        merkle_tree: &AccountInfo,
+        spl_account_compression_program_id: &AccountInfo,
+    ) -> ProgramResult {
+        // the V1 tree lives under spl-account-compression, the V2 tree under mpl-account-compression
+        let no_tree = Pubkey::default();
+
+        assert_true(
+            *spl_account_compression_program_id.key == spl_account_compression::id() ||
+            *spl_account_compression_program_id.key == MPL_ACCOUNT_COMPRESSION_ID,
+            ProgramError::from(McPayError::InvalidSPLAccountCompressionProgramID),
+            "CERROR: Invalid SPL Account Compression Program ID",
+        )?;
+
+        let is_v1_tree = *merkle_tree.key == program_state_data.merkle_tree &&
+            *spl_account_compression_program_id.key == spl_account_compression::id();
+        let is_v2_tree = program_state_data.merkle_tree_v2 != no_tree &&
+            *merkle_tree.key == program_state_data.merkle_tree_v2 &&
+            *spl_account_compression_program_id.key == MPL_ACCOUNT_COMPRESSION_ID;
+        assert_true(
+            (is_v1_tree || is_v2_tree) && merkle_tree.owner == spl_account_compression_program_id.key,
+            ProgramError::from(McPayError::InvalidMerkleTree),
+            "CERROR: Invalid merkle tree",
+        )?;
+
+        Ok(())
+    }
+
+    // splits the clock in fee between the vault and the treasury
+    fn collect_clock_in_fee<'a>(
+        program_state_data: &ProgramState,
+        fee_lamports: u64,
+        payer: &AccountInfo<'a>,
+        mcpay_vault_pda: &AccountInfo<'a>,
+        mcdegens_treasury: &AccountInfo<'a>,
+        system_program_id: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        if fee_lamports == 0 {
+            return Ok(());
+        }
+
+        let treasury_lamports = Self::apply_bps(fee_lamports, program_state_data.fee_treasury_bps as u64)?;
+        let vault_lamports = fee_lamports - treasury_lamports;
+
+        if vault_lamports > 0 {
+            msg!("Transferring SOL");
+            invoke(
+                &system_instruction::transfer(
+                    payer.key,
+                    mcpay_vault_pda.key,
+                    vault_lamports,
+                ),
+                &[
+                    payer.clone(),
+                    mcpay_vault_pda.clone(),
+                    system_program_id.clone(),
+                ],
+            )?;
+        }
+
+        if treasury_lamports > 0 {
+            msg!("Transferring SOL to Treasury");
+            invoke(
+                &system_instruction::transfer(
+                    payer.key,
+                    mcdegens_treasury.key,
+                    treasury_lamports,
+                ),
+                &[
+                    payer.clone(),
+                    mcdegens_treasury.clone(),
+                    system_program_id.clone(),
+                ],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // checks shared by every clock out, returns the program state to update
+    #[allow(clippy::too_many_arguments)]
+    fn clock_out_program_state<'a>(
+        program_id: &Pubkey,
+        program_state_pda: &AccountInfo<'a>,
+        mcpay_vault_pda: &AccountInfo<'a>,
+        mcpay_vault_pickle_ata: &AccountInfo<'a>,
+        pickle_mint: &AccountInfo<'a>,
+    ) -> Result<ProgramState, ProgramError> {
+        let program_state_data: ProgramState = Self::load_program_state(program_id, program_state_pda)?;
+        assert_true(
+            program_state_data.is_initialized,
+            ProgramError::from(McPayError::ProgramStateNotInitialized),
+            "CERROR: Program state not initialized",
+        )?;
+
+        assert_true(
+            program_state_data.clock_out_is_enabled == 1,
+            ProgramError::from(McPayError::ClockOutDisabled),
+            "CERROR: Clock out disabled",
+        )?;
+
+        assert_true(
+            *mcpay_vault_pda.key == program_state_data.mcpay_vault_pda,
+            ProgramError::from(McPayError::InvalidMcPayVaultPDA),
+            "CERROR: Invalid mcpay vault pda",
+        )?;
+
+        assert_true(
+            *mcpay_vault_pickle_ata.key == program_state_data.mcpay_vault_pickle_ata,
+            ProgramError::from(McPayError::InvalidMcPayVaultPickleATA),
+            "CERROR: Invalid mcpay vault pickle ata",
+        )?;
+
+        assert_true(
+            *pickle_mint.key == program_state_data.pickle_mint,
+            ProgramError::from(McPayError::InvalidPickleMint),
+            "CERROR: Invalid pickle mint",
+        )?;
+
+        Ok(program_state_data)
+    }
+
+    // closes the asset state of an already verified asset once due, returns the closed asset state
+    #[allow(clippy::too_many_arguments)]
+    fn clock_out_asset<'a>(
+        program_id: &Pubkey,
+        program_state_data: &ProgramState,
+        asset_state_pda: &AccountInfo<'a>,
+        asset_flag_pda: &AccountInfo<'a>,
+        clock_in_wallet: &AccountInfo<'a>,
+        rent_payer: &AccountInfo<'a>,
+        payout_wallet: &AccountInfo<'a>,
+        asset_id: Pubkey,
+        asset_owner: &Pubkey,
+        utime: i64,
+    ) -> Result<AssetState, ProgramError> {
+        if asset_state_pda.data_is_empty() {
+            msg!("CERROR: Asset not clocked in");
+            return Err(McPayError::NotClockedIn.into());
+        }
+        let mut asset_state_data: AssetState = AssetState::load(asset_state_pda, program_id)?;
+
+        let (asset_state, _asset_state_bump) = Self::stored_bump_address(
+            &[
+                b"asset-state",
+                asset_id.as_ref(),
+            ],
+            asset_state_data.bump,
+            program_id,
+            McPayError::InvalidAssetStatePDA,
+        )?;
+        assert_true(
+            asset_state == *asset_state_pda.key,
+            ProgramError::from(McPayError::InvalidAssetStatePDA),
+            "CERROR: Invalid asset state pda",
+        )?;
+
+        // a denied asset still clocks out, which thaws a frozen core or legacy asset, but it earns nothing
+        let (asset_flag, _asset_flag_bump) = Self::asset_flag(
+            program_id,
+            &asset_id,
+            asset_flag_pda,
+            asset_state_data.asset_flag_bump,
+            true,
+        )?;
+        if asset_flag == ASSET_FLAG_DENY {
+            msg!("Asset denied, clocking out without payout");
+            asset_state_data.chips_due = 0;
+        }
+
+        // pay the asset owner unless the policy routes chips elsewhere
+        let no_recipient = Pubkey::default();
+        let payout_wallet_key = if program_state_data.payout_policy == PAYOUT_CLOCK_IN_WALLET {
+            asset_state_data.clock_in_wallet
+        } else if program_state_data.payout_policy == PAYOUT_RECIPIENT && asset_state_data.payout_recipient != no_recipient {
+            asset_state_data.payout_recipient
+        } else {
+            *asset_owner
+        };
+        assert_true(
+            *payout_wallet.key == payout_wallet_key,
+            ProgramError::from(McPayError::InvalidPayoutWallet),
+            "CERROR: Invalid payout wallet",
+        )?;
+
+        assert_true(
+            *clock_in_wallet.key == asset_state_data.clock_in_wallet,
+            ProgramError::from(McPayError::InvalidClockInWallet),
+            "CERROR: Invalid clock in wallet",
+        )?;
+
+        assert_true(
+            *rent_payer.key == Self::asset_rent_payer(&asset_state_data),
+            ProgramError::from(McPayError::InvalidRentPayer),
+            "CERROR: Invalid rent payer",
+        )?;
+
+        if asset_state_data.clock_out_utime > utime {
+            msg!("CERROR: To soon");
+            return Err(McPayError::TooSoon.into());
+        }
+
+        // emptied now so a batch can't list it twice, its rent is refunded by refund_asset_rent
+        msg!("Closing Asset State");
+        assert_writable(asset_state_pda)?;
+        *asset_state_pda.try_borrow_mut_data()? = &mut [];
+
+        Ok(asset_state_data)
+    }
+
+    // refunds the closed asset states' rent once the clock out's invokes are done, lamports moved
+    // before an invoke that doesn't list both accounts leave the instruction unbalanced
+    fn refund_asset_rent(
+        asset_state_pdas: &[&AccountInfo],
+        rent_payer: &AccountInfo,
+    ) -> ProgramResult {
+        for asset_state_pda in asset_state_pdas.iter() {
+            **rent_payer.try_borrow_mut_lamports()? = rent_payer
+                .lamports()
+                .checked_add(asset_state_pda.lamports())
+                .ok_or(McPayError::AmountOverflow)?;
+            **asset_state_pda.try_borrow_mut_lamports()? = 0;
+        }
+
+        Ok(())
+    }
+
+    // burns, vests or pays out the chips of the closed asset states in one pass
+    #[allow(clippy::too_many_arguments)]
+    fn settle_clock_out<'a>(
+        program_id: &Pubkey,
+        program_state_data: &mut ProgramState,
+        asset_states: &[AssetState],
+        signer: &AccountInfo<'a>,
+        rent_payer: &AccountInfo<'a>,
+        asset_owner: &Pubkey,
+        program_state_pda: &AccountInfo<'a>,
+        spl_token_program_id: &AccountInfo<'a>,
+        mcpay_vault_pda: &AccountInfo<'a>,
+        mcpay_vault_pickle_ata: &AccountInfo<'a>,
+        payout_pickle_ata: &AccountInfo<'a>,
+        payout_wallet: &AccountInfo<'a>,
+        clock_in_wallet: &AccountInfo<'a>,
+        wallet_state_pda: &AccountInfo<'a>,
+        referrer_wallet_state_pda: &AccountInfo<'a>,
+        season_pda: &AccountInfo<'a>,
+        season_record_pda: &AccountInfo<'a>,
+        vesting_pda: &AccountInfo<'a>,
+        pickle_mint: &AccountInfo<'a>,
+        system_program_id: &AccountInfo<'a>,
+        spl_associated_token_program_id: &AccountInfo<'a>,
+        utime: i64,
+    ) -> ProgramResult {
+        // a sponsor that co-signs the clock out also funds the accounts it creates
+        let payer = if rent_payer.is_signer { rent_payer } else { signer };
+
+        let payout_pickle = get_associated_token_address(
+            &payout_wallet.key,
+            &program_state_data.pickle_mint
+        );
+        assert_true(
+            *payout_pickle_ata.key == payout_pickle,
+            ProgramError::from(McPayError::InvalidATA),
+            "CERROR: Invalid payout pickle ata",
+        )?;
+
+        let (wallet_state_data, wallet_state_bump) = Self::load_or_find_pda::<WalletState>(
+            program_id,
+            wallet_state_pda,
+            &[
+                b"wallet-state",
+                clock_in_wallet.key.as_ref(),
+            ],
+            McPayError::InvalidWalletStatePDA,
+        )?;
+
+        let mut chips_due: u64 = 0;
+        let mut burn_chips: u64 = 0;
+        let mut vesting_chips: u64 = 0;
+        let mut referral_chips: u64 = 0;
+        let mut season_points: u64 = 0;
+        for asset_state_data in asset_states.iter() {
+            // a denied asset clocks out without chips or season points
+            if asset_state_data.chips_due == 0 {
+                continue;
+            }
+            let asset_burn_chips = Self::apply_bps(asset_state_data.chips_due, program_state_data.burn_bps as u64)?;
+            if asset_state_data.level == 30 && program_state_data.vesting_is_enabled == 1 {
+                vesting_chips = vesting_chips
+                    .checked_add(asset_state_data.chips_due - asset_burn_chips)
+                    .ok_or(McPayError::AmountOverflow)?;
+            }
+            chips_due = chips_due
+                .checked_add(asset_state_data.chips_due)
+                .ok_or(McPayError::AmountOverflow)?;
+            burn_chips = burn_chips
+                .checked_add(asset_burn_chips)
+                .ok_or(McPayError::AmountOverflow)?;
+            referral_chips = referral_chips
+                .checked_add(Self::apply_bps(asset_state_data.chips_due, program_state_data.referral_bps as u64)?)
+                .ok_or(McPayError::AmountOverflow)?;
+            season_points = season_points
+                .checked_add(asset_state_data.level as u64)
+                .ok_or(McPayError::AmountOverflow)?;
+        }
+        let transfer_chips = chips_due - burn_chips - vesting_chips;
+
+        // a referrer's credit is reserved on top of the payout, the vault has to cover both
+        let no_referrer = Pubkey::default();
+        let credits_referrer = referral_chips > 0 && wallet_state_data
+            .as_ref()
+            .is_some_and(|wallet_state_data| wallet_state_data.referrer != no_referrer);
+        let reserved_chips = if credits_referrer {
+            chips_due
+                .checked_add(referral_chips)
+                .ok_or(McPayError::AmountOverflow)?
+        } else {
+            chips_due
+        };
+
+        let mcpay_vault_pickle_ata_data = unpack_token_account(mcpay_vault_pickle_ata)?;
+        assert_true(
+            Self::unreserved_chips(program_state_data, mcpay_vault_pickle_ata_data.amount) >= reserved_chips,
+            ProgramError::from(McPayError::InsufficientVaultPickle),
+            "CERROR: Insufficient funds in Pickle Vault",
+        )?;
+
+        if burn_chips > 0 {
+            msg!("Burning Pickle");
+            let burn_vault_pickle_ix = spl_token::instruction::burn(
+                spl_token_program_id.key,
+                mcpay_vault_pickle_ata.key,
+                pickle_mint.key,
+                &mcpay_vault_pda.key,
+                &[],
+                burn_chips,
+            )?;
+            invoke_signed(
+                &burn_vault_pickle_ix,
+                &[
+                    mcpay_vault_pickle_ata.clone(),
+                    pickle_mint.clone(),
+                    mcpay_vault_pda.clone(),
+                ],
+                &[&[b"mcpay-vault", &[program_state_data.mcpay_vault_bump]]],
+            )?;
+            program_state_data.total_burned = program_state_data.total_burned
+                .checked_add(burn_chips)
+                .ok_or(McPayError::AmountOverflow)?;
+        }
+
+        if vesting_chips > 0 {
+            let (vesting_state_data, vesting_bump) = Self::load_or_find_pda::<VestingState>(
+                program_id,
+                vesting_pda,
+                &[
+                    b"vesting",
+                    payout_wallet.key.as_ref(),
+                ],
+                McPayError::InvalidVestingPDA,
+            )?;
+
+            let mut vesting_state_data: VestingState = if let Some(vesting_state_data) = vesting_state_data {
+                vesting_state_data
+            } else {
+                msg!("Creating Vesting State");
+                let vesting_size = VestingState::LEN;
+                invoke_signed(
+                    &system_instruction::create_account(
+                        payer.key,
+                        &vesting_pda.key,
+                        Rent::get()?.minimum_balance(vesting_size),
+                        vesting_size as u64,
+                        program_id,
+                    ),
+                    &[
+                        payer.clone(),
+                        vesting_pda.clone(),
+                        system_program_id.clone(),
+                    ],
+                    &[&[
+                        b"vesting",
+                        payout_wallet.key.as_ref(),
+                        &[vesting_bump],
+                    ]],
+                )?;
+                VestingState::init(vesting_pda, program_id)?
+            };
+            vesting_state_data.bump = vesting_bump;
+            if !vesting_state_data.is_initialized {
+                vesting_state_data.is_initialized = true;
+                vesting_state_data.wallet = *payout_wallet.key;
+            }
+
+            // the new payout vests on a free tranche, the tranches already vesting keep their windows
+            msg!("Vesting Pickle");
+            Self::release_vested(&mut vesting_state_data, utime)?;
+            let end_utime = utime
+                .checked_add(program_state_data.vesting_seconds)
+                .ok_or(McPayError::AmountOverflow)?;
+            if let Some(tranche) = vesting_state_data.tranches.iter_mut().find(|tranche| tranche.locked_chips == 0) {
+                *tranche = VestingTranche {
+                    start_utime: utime,
+                    end_utime,
+                    locked_chips: vesting_chips,
+                    released_chips: 0,
+                };
+            } else {
+                // every tranche is taken, the payout joins the one that ends last and its remainder restarts with it
+                let tranche = vesting_state_data.tranches
+                    .iter_mut()
+                    .max_by_key(|tranche| tranche.end_utime)
+                    .ok_or(McPayError::AmountOverflow)?;
+                *tranche = VestingTranche {
+                    start_utime: utime,
+                    end_utime,
+                    locked_chips: tranche.locked_chips.saturating_sub(tranche.released_chips)
+                        .checked_add(vesting_chips)
+                        .ok_or(McPayError::AmountOverflow)?,
+                    released_chips: 0,
+                };
+            }
+            vesting_state_data.store(vesting_pda)?;
+
+            program_state_data.total_vesting_chips = program_state_data.total_vesting_chips
+                .checked_add(vesting_chips)
+                .ok_or(McPayError::AmountOverflow)?;
+        }
+
+        if transfer_chips > 0 {
+            if payout_pickle_ata.data_is_empty() {
+                msg!("Creating Payout Pickle ATA");
+                invoke(
+                    &create_associated_token_account_idempotent(
+                        payer.key,
+                        payout_wallet.key,
+                        pickle_mint.key,
+                        spl_token_program_id.key,
+                    ),
+                    &[
+                        payer.clone(),
+                        payout_pickle_ata.clone(),
+                        payout_wallet.clone(),
+                        pickle_mint.clone(),
+                        system_program_id.clone(),
+                        spl_token_program_id.clone(),
+                        spl_associated_token_program_id.clone(),
+                    ],
+                )?;
+            }
+
+            msg!("Transferring Pickle");
+            let transfer_vault_pickle_ix = spl_token::instruction::transfer(
+                spl_token_program_id.key,
+                mcpay_vault_pickle_ata.key,
+                payout_pickle_ata.key,
+                &mcpay_vault_pda.key,
+                &[],
+                transfer_chips,
+            )?;
+            invoke_signed(
+                &transfer_vault_pickle_ix,
+                &[
+                    mcpay_vault_pickle_ata.clone(),
+                    payout_pickle_ata.clone(),
+                    mcpay_vault_pda.clone(),
+                ],
+                &[&[b"mcpay-vault", &[program_state_data.mcpay_vault_bump]]],
+            )?;
+        }
+
+        let asset_count = asset_states.len() as u32;
+        if let Some(mut wallet_state_data) = wallet_state_data {
+            wallet_state_data.assets_clocked_in = wallet_state_data.assets_clocked_in.saturating_sub(asset_count);
+            wallet_state_data.bump = wallet_state_bump;
+            wallet_state_data.store(wallet_state_pda)?;
+
+            if credits_referrer {
+                let mut referrer_wallet_state_data: WalletState = WalletState::load(referrer_wallet_state_pda, program_id)?;
+                let (referrer_wallet_state, _referrer_wallet_state_bump) = Self::stored_bump_address(
+                    &[
+                        b"wallet-state",
+                        wallet_state_data.referrer.as_ref(),
+                    ],
+                    referrer_wallet_state_data.bump,
+                    program_id,
+                    McPayError::InvalidWalletStatePDA,
+                )?;
+                assert_true(
+                    referrer_wallet_state == *referrer_wallet_state_pda.key,
+                    ProgramError::from(McPayError::InvalidWalletStatePDA),
+                    "CERROR: Invalid referrer wallet state pda",
+                )?;
+
+                msg!("Crediting {} referral chips", referral_chips);
+                referrer_wallet_state_data.referral_chips_due = referrer_wallet_state_data.referral_chips_due
+                    .checked_add(referral_chips)
+                    .ok_or(McPayError::AmountOverflow)?;
+                referrer_wallet_state_data.store(referrer_wallet_state_pda)?;
+
+                program_state_data.total_referral_chips_due = program_state_data.total_referral_chips_due
+                    .checked_add(referral_chips)
+                    .ok_or(McPayError::AmountOverflow)?;
+            }
+        }
+
+        program_state_data.assets_clocked_in = program_state_data.assets_clocked_in.saturating_sub(asset_count);
+        program_state_data.store(program_state_pda)?;
+
+        if program_state_data.current_season_id > 0 && season_points > 0 {
+            let season_id_bytes = program_state_data.current_season_id.to_le_bytes();
+            let (season_state_data, _season_bump) = Self::load_or_find_pda::<SeasonState>(
+                program_id,
+                season_pda,
+                &[
+                    b"season",
+                    season_id_bytes.as_ref(),
+                ],
+                McPayError::InvalidSeasonPDA,
+            )?;
+
+            // a closed season is gone and collects no more points
+            let season_state_data = season_state_data.filter(|season_state_data| {
+                season_state_data.start_utime <= utime && utime <= season_state_data.end_utime
+            });
+            if let Some(mut season_state_data) = season_state_data {
+                let (season_record_data, season_record_bump) = Self::load_or_find_pda::<SeasonRecord>(
+                    program_id,
+                    season_record_pda,
+                    &[
+                        b"season-record",
+                        season_id_bytes.as_ref(),
+                        asset_owner.as_ref(),
+                    ],
+                    McPayError::InvalidSeasonRecordPDA,
+                )?;
+
+                let mut season_record_data: SeasonRecord = if let Some(season_record_data) = season_record_data {
+                    season_record_data
+                } else {
+                    msg!("Creating Season Record");
+                    let season_record_size = SeasonRecord::LEN;
+                    invoke_signed(
+                        &system_instruction::create_account(
+                            payer.key,
+                            &season_record_pda.key,
+                            Rent::get()?.minimum_balance(season_record_size),
+                            season_record_size as u64,
+                            program_id,
+                        ),
+                        &[
+                            payer.clone(),
+                            season_record_pda.clone(),
+                            system_program_id.clone(),
+                        ],
+                        &[&[
+                            b"season-record",
+                            season_id_bytes.as_ref(),
+                            asset_owner.as_ref(),
+                            &[season_record_bump],
+                        ]],
+                    )?;
+                    SeasonRecord::init(season_record_pda, program_id)?
+                };
+                season_record_data.bump = season_record_bump;
+                if !season_record_data.is_initialized {
+                    season_record_data.is_initialized = true;
+                    season_record_data.season_id = program_state_data.current_season_id;
+                    season_record_data.wallet = *asset_owner;
+                }
+
+                msg!("Adding {} Season Points", season_points);
+                season_record_data.points = season_record_data.points
+                    .checked_add(season_points)
+                    .ok_or(McPayError::AmountOverflow)?;
+                season_state_data.total_points = season_state_data.total_points
+                    .checked_add(season_points)
+                    .ok_or(McPayError::AmountOverflow)?;
+                season_record_data.store(season_record_pda)?;
+                season_state_data.store(season_pda)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // the signer is either the leaf owner or the leaf delegate acting for them
+    #[allow(clippy::too_many_arguments)]
+    fn verify_leaf<'a>(
+        signer: &AccountInfo<'a>,
+        leaf_owner: &AccountInfo<'a>,
+        leaf_delegate: &AccountInfo<'a>,
+        merkle_tree: &AccountInfo<'a>,
+        spl_account_compression_program_id: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+        asset_id: Pubkey,
+        nonce: u64,
+        root: &Pubkey,
+        data_hash: &Pubkey,
+        creator_hash: &Pubkey,
+        leaf_v2: &Option<LeafV2Data>,
+    ) -> ProgramResult {
+        assert_true(
+            *signer.key == *leaf_owner.key || *signer.key == *leaf_delegate.key,
+            ProgramError::from(McPayError::InvalidLeafOwner),
+            "CERROR: Signer is not the leaf owner or delegate",
+        )?;
+
+        let proof_length = Self::canopy_proof_length(merkle_tree)?;
+        if remaining_accounts.len() != proof_length {
+            msg!("CERROR: Expected {} proof accounts, got {}", proof_length, remaining_accounts.len());
+            return Err(McPayError::InvalidProofLength.into());
+        }
+
+        // V1 leaves sit in spl-account-compression trees, V2 leaves in mpl-account-compression trees
+        assert_true(
+            leaf_v2.is_some() == (*spl_account_compression_program_id.key != spl_account_compression::id()),
+            ProgramError::from(McPayError::InvalidLeafVersion),
+            "CERROR: Leaf version does not match the merkle tree",
+        )?;
+
+        // the leaf index is the nonce, trees are at most 2^30 leaves deep
+        let index = u32::try_from(nonce).map_err(|_| McPayError::InvalidLeafIndex)?;
+
+        if let Some(leaf_v2) = leaf_v2 {
+            let leaf_hash = leaf_v2.leaf_hash(
+                &asset_id,
+                leaf_owner.key,
+                leaf_delegate.key,
+                nonce,
+                data_hash,
+                creator_hash,
+            );
+
+            // verify_leaf discriminator | root | leaf | index
+            let mut verify_leaf_data: Vec<u8> = VERIFY_LEAF_DISCRIMINATOR.to_vec();
+            verify_leaf_data.extend_from_slice(root.as_ref());
+            verify_leaf_data.extend_from_slice(&leaf_hash);
+            verify_leaf_data.extend_from_slice(&index.to_le_bytes());
+
+            let mut verify_leaf_accounts = vec![AccountMeta::new_readonly(*merkle_tree.key, false)];
+            let mut verify_leaf_account_infos = vec![merkle_tree.clone()];
+            for account in remaining_accounts.iter() {
+                verify_leaf_accounts.push(AccountMeta::new_readonly(*account.key, false));
+                verify_leaf_account_infos.push(account.clone());
+            }
+
+            invoke(
+                &Instruction {
+                    program_id: *spl_account_compression_program_id.key,
+                    accounts: verify_leaf_accounts,
+                    data: verify_leaf_data,
+                },
+                &verify_leaf_account_infos,
+            )?;
+
+            return Ok(());
+        }
+
+        let leaf = mpl_bubblegum::types::LeafSchema::V1 {
+            id: asset_id,
+            owner: *leaf_owner.key,
+            delegate: *leaf_delegate.key,
+            nonce,
+            data_hash: data_hash.to_bytes(),
+            creator_hash: creator_hash.to_bytes(),
+        };
+
+        let verify_leaf_cpi = mpl_bubblegum::instructions::VerifyLeafCpi::new(
+            spl_account_compression_program_id,
+            mpl_bubblegum::instructions::VerifyLeafCpiAccounts {
+                merkle_tree,
+            },
+            mpl_bubblegum::instructions::VerifyLeafInstructionArgs {
+                index,
+                leaf: leaf.hash(),
+                root: root.to_bytes(),
+            }
+        );
+        verify_leaf_cpi.invoke_with_remaining_accounts(
+            remaining_accounts
+                .iter()
+                .map(|account| (account, false, false))
+                .collect::<Vec<_>>()
+                .as_slice()
+        )?;
+
+        Ok(())
+    }
+
+    // max depth less the canopy depth, read from the ConcurrentMerkleTree account
+    // header (2 + 54) | tree (24 + max_buffer_size * (32 * max_depth + 40) + 32 * max_depth + 40) | canopy
+    fn canopy_proof_length(
+        merkle_tree: &AccountInfo,
+    ) -> Result<usize, ProgramError> {
+        let merkle_tree_data = merkle_tree.try_borrow_data()?;
+        assert_true(
+            merkle_tree_data.len() >= 56 && merkle_tree_data[0] == 1 && merkle_tree_data[1] == 0,
+            ProgramError::from(McPayError::InvalidMerkleTreeHeader),
+            "CERROR: Invalid merkle tree header",
+        )?;
+
+        let max_buffer_size = u32::from_le_bytes(merkle_tree_data[2..6].try_into().unwrap()) as usize;
+        let max_depth = u32::from_le_bytes(merkle_tree_data[6..10].try_into().unwrap()) as usize;
+        let path_size = 32 * max_depth + 40;
+        let tree_size = max_buffer_size
+            .checked_mul(path_size)
+            .and_then(|size| size.checked_add(24 + path_size))
+            .ok_or(McPayError::InvalidMerkleTreeHeader)?;
+        let canopy_bytes = merkle_tree_data.len()
+            .checked_sub(56 + tree_size)
+            .ok_or(McPayError::InvalidMerkleTreeHeader)?;
+
+        // the canopy caches a full tree less its root, 2^(n + 1) - 2 nodes for depth n
+        let canopy_nodes = canopy_bytes / 32 + 2;
+        assert_true(
+            canopy_bytes % 32 == 0 && canopy_nodes.is_power_of_two(),
+            ProgramError::from(McPayError::InvalidCanopy),
+            "CERROR: Invalid merkle tree canopy",
+        )?;
+        let canopy_depth = canopy_nodes.trailing_zeros() as usize - 1;
+        assert_true(
+            canopy_depth <= max_depth,
+            ProgramError::from(McPayError::InvalidCanopy),
+            "CERROR: Invalid merkle tree canopy",
+        )?;
+
+        Ok(max_depth - canopy_depth)
+    }
+
+    // moves the body behind a fresh header and grows the account to the current layout,
+    // the signer tops up rent for the extra space
+    fn migrate_account<'a, T: AccountState>(
+        program_id: &Pubkey,
+        signer: &AccountInfo<'a>,
+        state_account: &AccountInfo<'a>,
+        system_program_id: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        let state: T = {
+            let state_data = state_account.data.borrow();
+            if state_data.len() >= ACCOUNT_HEADER_SIZE && state_data[..8] == T::DISCRIMINATOR {
+                assert_true(
+                    state_data[8] <= T::VERSION && state_data.len() <= T::LEN,
+                    ProgramError::from(McPayError::InvalidAccountVersion),
+                    "CERROR: Account is newer than the current layout",
+                )?;
+                T::migrate_from(state_data[8], &state_data[ACCOUNT_HEADER_SIZE..])?
+            } else {
+                // legacy accounts have no header, just the borsh body
+                assert_true(
+                    state_data.len() <= T::SIZE,
+                    ProgramError::from(McPayError::InvalidAccountDiscriminator),
+                    "CERROR: Account is not a legacy account of this type",
+                )?;
+                T::migrate_from(0, &state_data)?
+            }
+        };
+
+        // the length alone does not tell the account types apart, the address must be this type's pda
+        let seeds = state.seeds();
+        let seeds: Vec<&[u8]> = seeds.iter().map(|seed| seed.as_slice()).collect();
+        let (state_address, _bump) = Pubkey::find_program_address(&seeds, program_id);
+        assert_true(
+            state_address == *state_account.key,
+            ProgramError::from(McPayError::InvalidAccountType),
+            "CERROR: Account is not a pda of this account type",
+        )?;
+
+        let rent_lamports = Rent::get()?.minimum_balance(T::LEN);
+        if state_account.lamports() < rent_lamports {
+            invoke(
+                &system_instruction::transfer(
+                    signer.key,
+                    state_account.key,
+                    rent_lamports - state_account.lamports(),
+                ),
+                &[
+                    signer.clone(),
+                    state_account.clone(),
+                    system_program_id.clone(),
+                ],
+            )?;
+        }
+
+        state_account.realloc(T::LEN, true)?;
+
+        let mut state_data = state_account.data.borrow_mut();
+        state_data.fill(0);
+        state.pack(&mut state_data)?;
+
+        // the migrated account must read back under the current layout
+        T::unpack(&state_data)?;
+
+        Ok(())
+    }
+
+    // flag an admin has set on the asset, 0 when there is none, and the flag pda's bump; a set flag is
+    // checked with the bump it stores, an empty pda with asset_flag_bump, which has to be canonical
+    // unless the asset state stored it at clock in
+    fn asset_flag(
+        program_id: &Pubkey,
+        asset_id: &Pubkey,
+        asset_flag_pda: &AccountInfo,
+        asset_flag_bump: u8,
+        bump_is_stored: bool,
+    ) -> Result<(u8, u8), ProgramError> {
+        let seeds: &[&[u8]] = &[
+            b"asset-flag",
+            asset_id.as_ref(),
+        ];
+        let (flag, (asset_flag, asset_flag_bump)) = if !asset_flag_pda.data_is_empty() {
+            let asset_flag_data: AssetFlag = AssetFlag::load(asset_flag_pda, program_id)?;
+            let asset_flag_address = Self::stored_bump_address(seeds, asset_flag_data.bump, program_id, McPayError::InvalidAssetFlagPDA)?;
+            (asset_flag_data.flag, asset_flag_address)
+        } else if bump_is_stored {
+            (0, Self::stored_bump_address(seeds, asset_flag_bump, program_id, McPayError::InvalidAssetFlagPDA)?)
+        } else {
+            (0, Self::canonical_bump_address(seeds, asset_flag_bump, program_id, McPayError::InvalidAssetFlagPDA)?)
+        };
+        assert_true(
+            asset_flag == *asset_flag_pda.key,
+            ProgramError::from(McPayError::InvalidAssetFlagPDA),
+            "CERROR: Invalid asset flag pda",
+        )?;
+
+        Ok((flag, asset_flag_bump))
+    }
+
+    // wallet refunded the asset state rent, asset states written before rent_payer existed
+    // read it as zero and were paid for by the clock in wallet
+    fn asset_rent_payer(
+        asset_state: &AssetState,
+    ) -> Pubkey {
+        if asset_state.rent_payer == Pubkey::default() {
+            asset_state.clock_in_wallet
+        } else {
+            asset_state.rent_payer
+        }
+    }
+
+    // vault pickle not owed to vesting wallets, undistributed season pools or referrers
+    fn unreserved_chips(
+        program_state: &ProgramState,
+        vault_chips: u64,
+    ) -> u64 {
+        vault_chips
+            .saturating_sub(program_state.total_vesting_chips)
+            .saturating_sub(program_state.total_season_pool_chips)
+            .saturating_sub(program_state.total_referral_chips_due)
+    }
+
+    // locked_chips released linearly from start_utime to end_utime
+    fn vested_chips(
+        tranche: &VestingTranche,
+        utime: i64,
+    ) -> u64 {
+        if utime >= tranche.end_utime {
+            return tranche.locked_chips;
+        }
+        if utime <= tranche.start_utime {
+            return 0;
+        }
+        let elapsed = (utime - tranche.start_utime) as u128;
+        let duration = (tranche.end_utime - tranche.start_utime) as u128;
+        (tranche.locked_chips as u128 * elapsed / duration) as u64
+    }
+
+    // moves what every tranche has vested by utime into unlocked_chips and frees the tranches that are done
+    fn release_vested(
+        vesting_state: &mut VestingState,
+        utime: i64,
+    ) -> ProgramResult {
+        for tranche in vesting_state.tranches.iter_mut() {
+            let vested_chips = Self::vested_chips(tranche, utime);
+            vesting_state.unlocked_chips = vesting_state.unlocked_chips
+                .checked_add(vested_chips.saturating_sub(tranche.released_chips))
+                .ok_or(McPayError::AmountOverflow)?;
+            tranche.released_chips = vested_chips.max(tranche.released_chips);
+            if tranche.released_chips >= tranche.locked_chips {
+                *tranche = VestingTranche::default();
+            }
+        }
+
+        Ok(())
+    }
+
+    // rate multiplier of utilization_target / assets_clocked_in, clamped to the configured bounds
+    fn utilization_bps(
+        program_state: &ProgramState,
+    ) -> u16 {
+        let assets_clocked_in = program_state.assets_clocked_in.max(1) as u64;
+        let utilization_bps = 10_000 * program_state.utilization_target as u64 / assets_clocked_in;
+        utilization_bps.clamp(
+            program_state.utilization_min_bps as u64,
+            program_state.utilization_max_bps as u64,
+        ) as u16
+    }
+
+    // tier rate less emission_decay_bps for every full epoch since emission_start_utime, floored at emission_floor_rate per shift day
+    fn scheduled_rate(
+        program_state: &ProgramState,
+        rate: u64,
+        level: u8,
+        utime: i64,
+    ) -> u64 {
+        if program_state.emission_decay_bps == 0 ||
+            program_state.emission_epoch_seconds <= 0 ||
+            utime <= program_state.emission_start_utime {
+            return rate;
+        }
+        let epochs = (utime - program_state.emission_start_utime) / program_state.emission_epoch_seconds;
+
+        let scale: u128 = 1_000_000_000_000;
+        let mut factor = scale;
+        let mut decay = (10_000 - program_state.emission_decay_bps as u128) * scale / 10_000;
+        let mut exponent = epochs as u64;
+        while exponent > 0 && factor > 0 {
+            if exponent & 1 == 1 {
+                factor = factor * decay / scale;
+            }
+            decay = decay * decay / scale;
+            exponent >>= 1;
+        }
+
+        let decayed_rate = (rate as u128 * factor / scale) as u64;
+        let floor_rate = program_state.emission_floor_rate
+            .saturating_mul(level as u64)
+            .min(rate);
+        decayed_rate.max(floor_rate)
+    }
+
+    // highest bonus among the brackets the wallet's clocked in count reaches, min_assets 0 = unused
+    fn wallet_bonus_bps(
+        bonus_brackets: &[BonusBracket],
+        assets_clocked_in: u32,
+    ) -> u16 {
+        bonus_brackets
+            .iter()
+            .filter(|bonus_bracket| bonus_bracket.min_assets > 0 && assets_clocked_in >= bonus_bracket.min_assets)
+            .map(|bonus_bracket| bonus_bracket.bonus_bps)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn apply_bps(
+        chips: u64,
+        bps: u64,
+    ) -> Result<u64, ProgramError> {
+        let chips = (chips as u128)
+            .checked_mul(bps as u128)
+            .ok_or(McPayError::AmountOverflow)?
+            / 10_000;
+        u64::try_from(chips).map_err(|_| McPayError::AmountOverflow.into())
+    }
 }
\ No newline at end of file
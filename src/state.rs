@@ -1,51 +1,625 @@
 use borsh::{BorshDeserialize, BorshSerialize};
-use solana_program::pubkey::Pubkey;
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    keccak,
+    msg,
+    program_error::ProgramError,
+    pubkey,
+    pubkey::Pubkey,
+};
 
-#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
-pub struct ProgramState {  // 1 + 1 + 1 + 32 + 8 + 8 + 8 + 32 + 32 + 1 + 32 + 32 + 32
-    pub is_initialized: bool,
-    pub clock_in_is_enabled: u8,
-    pub clock_out_is_enabled: u8,
-    pub merkle_tree: Pubkey,
-    pub level_one_rate: u64,
-    pub level_seven_rate: u64,
-    pub level_thirty_rate: u64,
-    pub pickle_mint: Pubkey,
-    pub mcpay_vault_pda: Pubkey,
-    pub mcpay_vault_bump: u8,
-    pub mcpay_vault_pickle_ata: Pubkey,
-    pub mcdegens_treasury: Pubkey,
-    pub mcdegens_pickle_ata: Pubkey,
+use crate::error::McPayError;
+use crate::validation::{
+    assert_owner,
+    assert_writable,
+};
+
+pub const MAX_BONUS_BRACKETS: usize = 4;
+pub const MAX_VESTING_TRANCHES: usize = 8;
+
+// every program account is laid out as discriminator (8) | version (1) | borsh body | reserved padding
+pub const ACCOUNT_HEADER_SIZE: usize = 8 + 1;
+pub const ACCOUNT_RESERVED_SIZE: usize = 64;
+
+pub const ACCOUNT_TYPE_PROGRAM_STATE: u8 = 0;
+pub const ACCOUNT_TYPE_ASSET_STATE: u8 = 1;
+pub const ACCOUNT_TYPE_WALLET_STATE: u8 = 2;
+pub const ACCOUNT_TYPE_ASSET_FLAG: u8 = 3;
+pub const ACCOUNT_TYPE_VESTING_STATE: u8 = 4;
+pub const ACCOUNT_TYPE_TREASURY_STATE: u8 = 5;
+pub const ACCOUNT_TYPE_SEASON_STATE: u8 = 6;
+pub const ACCOUNT_TYPE_SEASON_RECORD: u8 = 7;
+
+// borsh size of a fixed size type, account bodies sum it over their fields
+pub trait BorshSize {
+    const BORSH_SIZE: usize;
+}
+
+macro_rules! impl_borsh_size {
+    ($($ty:ty => $size:expr),* $(,)?) => {
+        $(
+            impl BorshSize for $ty {
+                const BORSH_SIZE: usize = $size;
+            }
+        )*
+    };
+}
+
+impl_borsh_size!(bool => 1, u8 => 1, u16 => 2, u32 => 4, u64 => 8, i64 => 8, Pubkey => 32);
+
+impl<T: BorshSize, const N: usize> BorshSize for [T; N] {
+    const BORSH_SIZE: usize = T::BORSH_SIZE * N;
+}
+
+// declares a struct and its BorshSize as the sum of its field types
+macro_rules! borsh_sized {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident {
+            $($(#[$field_meta:meta])* pub $field:ident: $ty:ty,)*
+        }
+    ) => {
+        $(#[$meta])*
+        pub struct $name {
+            $($(#[$field_meta])* pub $field: $ty,)*
+        }
+
+        impl BorshSize for $name {
+            const BORSH_SIZE: usize = 0 $(+ <$ty as BorshSize>::BORSH_SIZE)*;
+        }
+    };
+}
+
+pub trait AccountState: BorshSerialize + BorshDeserialize + BorshSize + Default {
+    const DISCRIMINATOR: [u8; 8];
+    // bumped whenever the body layout changes, migrate_from reads every older version
+    const VERSION: u8;
+    // borsh size of the body
+    const SIZE: usize = Self::BORSH_SIZE;
+    const LEN: usize = ACCOUNT_HEADER_SIZE + Self::SIZE + ACCOUNT_RESERVED_SIZE;
+
+    // pda seeds of the account without the bump
+    fn seeds(&self) -> Vec<Vec<u8>>;
+
+    // bump of the account's pda, 0 when the account does not store it
+    fn bump(&self) -> u8 {
+        0
+    }
+
+    // body written under an older version (0 for a legacy account without a header) read into the current layout
+    fn migrate_from(version: u8, body: &[u8]) -> Result<Self, ProgramError>;
+
+    // the first `size` bytes of an older body, fields it did not have yet start at zero
+    fn read_body(body: &[u8], size: usize) -> Result<Self, ProgramError> {
+        let mut padded_body = body[..size.min(body.len())].to_vec();
+        padded_body.resize(Self::SIZE, 0);
+        Ok(Self::deserialize(&mut padded_body.as_slice())?)
+    }
+
+    fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() < ACCOUNT_HEADER_SIZE {
+            msg!("CERROR: Account data too small");
+            return Err(McPayError::InvalidAccountDiscriminator.into());
+        }
+        if data[..8] != Self::DISCRIMINATOR {
+            msg!("CERROR: Invalid account discriminator");
+            return Err(McPayError::InvalidAccountDiscriminator.into());
+        }
+        if data[8] != Self::VERSION {
+            msg!("CERROR: Account version {} needs migrating to {}", data[8], Self::VERSION);
+            return Err(McPayError::InvalidAccountVersion.into());
+        }
+
+        Ok(Self::deserialize(&mut &data[ACCOUNT_HEADER_SIZE..])?)
+    }
+
+    fn pack(&self, data: &mut [u8]) -> ProgramResult {
+        data[..8].copy_from_slice(&Self::DISCRIMINATOR);
+        data[8] = Self::VERSION;
+        self.serialize(&mut &mut data[ACCOUNT_HEADER_SIZE..])?;
+
+        Ok(())
+    }
+
+    // the owner is checked before any byte of the account is trusted
+    fn load(account: &AccountInfo, program_id: &Pubkey) -> Result<Self, ProgramError> {
+        assert_owner(account, program_id, McPayError::InvalidAccountOwner)?;
+        Self::unpack(&account.data.borrow())
+    }
+
+    // an account the program just created, still all zeros, the first store writes its header
+    fn init(account: &AccountInfo, program_id: &Pubkey) -> Result<Self, ProgramError> {
+        assert_owner(account, program_id, McPayError::InvalidAccountOwner)?;
+        let data = account.data.borrow();
+        if data.len() < Self::LEN || data[..ACCOUNT_HEADER_SIZE].iter().any(|byte| *byte != 0) {
+            msg!("CERROR: Account already initialized");
+            return Err(McPayError::InvalidAccountDiscriminator.into());
+        }
+
+        Ok(Self::default())
+    }
+
+    fn store(&self, account: &AccountInfo) -> ProgramResult {
+        assert_writable(account)?;
+        self.pack(&mut account.data.borrow_mut())
+    }
+}
+
+borsh_sized! {
+    #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy, Default)]
+    pub struct BonusBracket {
+        pub min_assets: u32,
+        pub bonus_bps: u16,
+    }
+}
+
+borsh_sized! {
+    #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Default)]
+    pub struct ProgramState {
+        pub is_initialized: bool,
+        pub clock_in_is_enabled: u8,
+        pub clock_out_is_enabled: u8,
+        pub merkle_tree: Pubkey,
+        pub level_one_rate: u64,
+        pub level_seven_rate: u64,
+        pub level_thirty_rate: u64,
+        pub pickle_mint: Pubkey,
+        pub mcpay_vault_pda: Pubkey,
+        pub mcpay_vault_bump: u8,
+        pub mcpay_vault_pickle_ata: Pubkey,
+        pub mcdegens_treasury: Pubkey,
+        pub mcdegens_pickle_ata: Pubkey,
+        pub bonus_brackets: [BonusBracket; MAX_BONUS_BRACKETS],
+        pub emission_start_utime: i64,
+        pub emission_epoch_seconds: i64,
+        pub emission_decay_bps: u16,
+        pub emission_floor_rate: u64,
+        pub emission_cap: u64,
+        pub emission_cap_epoch_seconds: i64,
+        pub emission_cap_epoch_start_utime: i64,
+        pub emission_committed: u64,
+        pub assets_clocked_in: u32,
+        pub utilization_target: u32,
+        pub utilization_min_bps: u16,
+        pub utilization_max_bps: u16,
+        pub current_season_id: u32,
+        pub referral_bps: u16,
+        pub level_one_fee: u64,
+        pub level_seven_fee: u64,
+        pub level_thirty_fee: u64,
+        pub fee_treasury_bps: u16,
+        pub burn_bps: u16,
+        pub total_burned: u64,
+        pub vesting_is_enabled: u8,
+        pub vesting_seconds: i64,
+        pub total_vesting_chips: u64,
+        pub allowlist_only: u8,
+        pub payout_policy: u8,
+        pub core_collection: Pubkey,
+        pub legacy_collection: Pubkey,
+        pub merkle_tree_v2: Pubkey,
+        pub program_state_bump: u8,
+        pub total_season_pool_chips: u64,
+        pub total_referral_chips_due: u64,
+    }
+}
+
+borsh_sized! {
+    #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Default)]
+    pub struct AssetState {
+        pub is_initialized: bool,
+        pub clock_in_wallet: Pubkey,
+        pub asset_id: Pubkey,
+        pub clock_in_utime: i64,
+        pub clock_out_utime: i64,
+        pub level: u8,
+        pub chips_due: u64,
+        pub payout_recipient: Pubkey,
+        pub rent_payer: Pubkey,
+        pub bump: u8,
+        pub asset_kind: u8,
+        pub asset_flag_bump: u8,
+    }
+}
+
+borsh_sized! {
+    #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Default)]
+    pub struct WalletState {
+        pub is_initialized: bool,
+        pub wallet: Pubkey,
+        pub assets_clocked_in: u32,
+        pub referrer: Pubkey,
+        pub referral_chips_due: u64,
+        pub bump: u8,
+    }
+}
+
+pub const PAYOUT_CURRENT_OWNER: u8 = 0;
+pub const PAYOUT_CLOCK_IN_WALLET: u8 = 1;
+pub const PAYOUT_RECIPIENT: u8 = 2;
+
+pub const ASSET_FLAG_DENY: u8 = 1;
+pub const ASSET_FLAG_ALLOW: u8 = 2;
+
+pub const ASSET_KIND_COMPRESSED: u8 = 0;
+pub const ASSET_KIND_CORE: u8 = 1;
+pub const ASSET_KIND_LEGACY: u8 = 2;
+
+pub const LEAF_VERSION_V2: u8 = 2;
+// anchor discriminator of the compression programs' verify_leaf, sha256("global:verify_leaf")[..8]
+pub const VERIFY_LEAF_DISCRIMINATOR: [u8; 8] = [124, 220, 22, 223, 104, 10, 250, 224];
+// owner of the V2 trees, V1 trees stay under spl-account-compression
+pub const MPL_ACCOUNT_COMPRESSION_ID: Pubkey = pubkey!("mcmt6YrQEMKw8Mw43FmpRLmf7BqRnFMKmAcbxE3xkAW");
+
+borsh_sized! {
+    #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Default)]
+    pub struct AssetFlag {
+        pub is_initialized: bool,
+        pub asset_id: Pubkey,
+        pub flag: u8,
+        pub bump: u8,
+    }
+}
+
+borsh_sized! {
+    #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Default)]
+    pub struct VestingTranche {
+        pub start_utime: i64,
+        pub end_utime: i64,
+        pub locked_chips: u64,
+        pub released_chips: u64,
+    }
+}
+
+borsh_sized! {
+    // every vested payout runs on its own tranche, a tranche with no locked chips is free
+    #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Default)]
+    pub struct VestingState {
+        pub is_initialized: bool,
+        pub wallet: Pubkey,
+        pub unlocked_chips: u64,
+        pub bump: u8,
+        pub tranches: [VestingTranche; MAX_VESTING_TRANCHES],
+    }
+}
+
+borsh_sized! {
+    #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Default)]
+    pub struct TreasuryState {
+        pub is_initialized: bool,
+        pub treasury_type: u8,
+        pub name: [u8; 32],
+        pub pickle_ata: Pubkey,
+        pub bump: u8,
+    }
+}
+
+borsh_sized! {
+    #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Default)]
+    pub struct SeasonState {
+        pub is_initialized: bool,
+        pub season_id: u32,
+        pub start_utime: i64,
+        pub end_utime: i64,
+        pub bonus_pool: u64,
+        pub total_points: u64,
+        pub distributed_chips: u64,
+        pub bump: u8,
+    }
+}
+
+borsh_sized! {
+    #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Default)]
+    pub struct SeasonRecord {
+        pub is_initialized: bool,
+        pub season_id: u32,
+        pub wallet: Pubkey,
+        pub points: u64,
+        pub bump: u8,
+    }
+}
+
+impl AccountState for ProgramState {
+    const DISCRIMINATOR: [u8; 8] = *b"PROGSTAT";
+    const VERSION: u8 = 2;
+
+    fn migrate_from(version: u8, body: &[u8]) -> Result<Self, ProgramError> {
+        match version {
+            // legacy and version 1 bodies end before program_state_bump and the reservation totals
+            0 | 1 => Self::read_body(body, Self::SIZE - (1 + 8 + 8)),
+            2 => Self::read_body(body, Self::SIZE),
+            _ => Err(McPayError::InvalidAccountVersion.into()),
+        }
+    }
+
+    fn seeds(&self) -> Vec<Vec<u8>> {
+        vec![b"program-state".to_vec()]
+    }
+
+    fn bump(&self) -> u8 {
+        self.program_state_bump
+    }
+}
+
+impl AccountState for AssetState {
+    const DISCRIMINATOR: [u8; 8] = *b"ASSTSTAT";
+    const VERSION: u8 = 3;
+
+    fn migrate_from(version: u8, body: &[u8]) -> Result<Self, ProgramError> {
+        match version {
+            // legacy and version 1 bodies end before bump and asset_kind, every one of them is compressed
+            0 | 1 => Self::read_body(body, Self::SIZE - (1 + 1 + 1)),
+            // version 2 bodies end before asset_flag_bump
+            2 => Self::read_body(body, Self::SIZE - 1),
+            3 => Self::read_body(body, Self::SIZE),
+            _ => Err(McPayError::InvalidAccountVersion.into()),
+        }
+    }
+
+    fn seeds(&self) -> Vec<Vec<u8>> {
+        vec![b"asset-state".to_vec(), self.asset_id.to_bytes().to_vec()]
+    }
+
+    fn bump(&self) -> u8 {
+        self.bump
+    }
+}
+
+impl AccountState for WalletState {
+    const DISCRIMINATOR: [u8; 8] = *b"WLLTSTAT";
+    const VERSION: u8 = 2;
+
+    fn migrate_from(version: u8, body: &[u8]) -> Result<Self, ProgramError> {
+        match version {
+            // legacy and version 1 bodies end before bump
+            0 | 1 => Self::read_body(body, Self::SIZE - 1),
+            2 => Self::read_body(body, Self::SIZE),
+            _ => Err(McPayError::InvalidAccountVersion.into()),
+        }
+    }
+
+    fn seeds(&self) -> Vec<Vec<u8>> {
+        vec![b"wallet-state".to_vec(), self.wallet.to_bytes().to_vec()]
+    }
+
+    fn bump(&self) -> u8 {
+        self.bump
+    }
+}
+
+impl AccountState for AssetFlag {
+    const DISCRIMINATOR: [u8; 8] = *b"ASSTFLAG";
+    const VERSION: u8 = 2;
+
+    fn migrate_from(version: u8, body: &[u8]) -> Result<Self, ProgramError> {
+        match version {
+            // legacy and version 1 bodies end before bump
+            0 | 1 => Self::read_body(body, Self::SIZE - 1),
+            2 => Self::read_body(body, Self::SIZE),
+            _ => Err(McPayError::InvalidAccountVersion.into()),
+        }
+    }
+
+    fn seeds(&self) -> Vec<Vec<u8>> {
+        vec![b"asset-flag".to_vec(), self.asset_id.to_bytes().to_vec()]
+    }
+
+    fn bump(&self) -> u8 {
+        self.bump
+    }
+}
+
+impl AccountState for VestingState {
+    const DISCRIMINATOR: [u8; 8] = *b"VESTSTAT";
+    const VERSION: u8 = 3;
+
+    fn migrate_from(version: u8, body: &[u8]) -> Result<Self, ProgramError> {
+        match version {
+            // up to version 2 a single schedule, the bump only from version 2, it becomes the first tranche
+            0..=2 => {
+                let mut padded_body = body.to_vec();
+                padded_body.resize(body.len().max(1 + 32 + 8 + 8 + 8 + 8 + 8 + 1), 0);
+                let (is_initialized, wallet, start_utime, end_utime, locked_chips, released_chips, unlocked_chips, bump) =
+                    <(bool, Pubkey, i64, i64, u64, u64, u64, u8)>::deserialize(&mut padded_body.as_slice())?;
+                let mut vesting_state = Self {
+                    is_initialized,
+                    wallet,
+                    unlocked_chips,
+                    bump: if version == 2 { bump } else { 0 },
+                    ..Self::default()
+                };
+                vesting_state.tranches[0] = VestingTranche {
+                    start_utime,
+                    end_utime,
+                    locked_chips,
+                    released_chips,
+                };
+                Ok(vesting_state)
+            }
+            3 => Self::read_body(body, Self::SIZE),
+            _ => Err(McPayError::InvalidAccountVersion.into()),
+        }
+    }
+
+    fn seeds(&self) -> Vec<Vec<u8>> {
+        vec![b"vesting".to_vec(), self.wallet.to_bytes().to_vec()]
+    }
+
+    fn bump(&self) -> u8 {
+        self.bump
+    }
+}
+
+impl AccountState for TreasuryState {
+    const DISCRIMINATOR: [u8; 8] = *b"TRSYSTAT";
+    const VERSION: u8 = 2;
+
+    fn migrate_from(version: u8, body: &[u8]) -> Result<Self, ProgramError> {
+        match version {
+            // legacy and version 1 bodies end before bump
+            0 | 1 => Self::read_body(body, Self::SIZE - 1),
+            2 => Self::read_body(body, Self::SIZE),
+            _ => Err(McPayError::InvalidAccountVersion.into()),
+        }
+    }
+
+    fn seeds(&self) -> Vec<Vec<u8>> {
+        vec![b"treasury".to_vec(), vec![self.treasury_type]]
+    }
+
+    fn bump(&self) -> u8 {
+        self.bump
+    }
+}
+
+impl AccountState for SeasonState {
+    const DISCRIMINATOR: [u8; 8] = *b"SEASSTAT";
+    const VERSION: u8 = 2;
+
+    fn migrate_from(version: u8, body: &[u8]) -> Result<Self, ProgramError> {
+        match version {
+            // legacy and version 1 bodies end before bump
+            0 | 1 => Self::read_body(body, Self::SIZE - 1),
+            2 => Self::read_body(body, Self::SIZE),
+            _ => Err(McPayError::InvalidAccountVersion.into()),
+        }
+    }
+
+    fn seeds(&self) -> Vec<Vec<u8>> {
+        vec![b"season".to_vec(), self.season_id.to_le_bytes().to_vec()]
+    }
+
+    fn bump(&self) -> u8 {
+        self.bump
+    }
+}
+
+impl AccountState for SeasonRecord {
+    const DISCRIMINATOR: [u8; 8] = *b"SEASRCRD";
+    const VERSION: u8 = 2;
+
+    fn migrate_from(version: u8, body: &[u8]) -> Result<Self, ProgramError> {
+        match version {
+            // legacy and version 1 bodies end before bump
+            0 | 1 => Self::read_body(body, Self::SIZE - 1),
+            2 => Self::read_body(body, Self::SIZE),
+            _ => Err(McPayError::InvalidAccountVersion.into()),
+        }
+    }
+
+    fn seeds(&self) -> Vec<Vec<u8>> {
+        vec![b"season-record".to_vec(), self.season_id.to_le_bytes().to_vec(), self.wallet.to_bytes().to_vec()]
+    }
+
+    fn bump(&self) -> u8 {
+        self.bump
+    }
 }
 
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
-pub struct AssetState {  // 1 + 32 + 32 + 8 + 8 + 1 + 8
-    pub is_initialized: bool,
-    pub clock_in_wallet: Pubkey,
-    pub asset_id: Pubkey,
-    pub clock_in_utime: i64,
-    pub clock_out_utime: i64,
+pub struct ClockInData {  // 32 + 32 + 32 + 8 + 1 + 1 + 32 + 32 + (1 + 65) + 1 + 1
+    pub root: Pubkey,
+    pub data_hash: Pubkey,
+    pub creator_hash: Pubkey,
+    pub nonce: u64,
+    pub proof_length: u8,
     pub level: u8,
-    pub chips_due: u64,
+    pub referrer: Pubkey,
+    pub payout_recipient: Pubkey,
+    #[borsh(deserialize_with = "deserialize_trailing")]
+    pub leaf_v2: Option<LeafV2Data>,
+    #[borsh(deserialize_with = "deserialize_trailing")]
+    pub asset_state_bump: u8,
+    #[borsh(deserialize_with = "deserialize_trailing")]
+    pub asset_flag_bump: u8,
 }
 
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
-pub struct ClockInData {  // 32 + 32 + 32 + 8 + 1 + 1
+pub struct ClockInEntry {  // 32 + 32 + 32 + 8 + 1 + 1 + (1 + 65) + 1 + 1
     pub root: Pubkey,
     pub data_hash: Pubkey,
     pub creator_hash: Pubkey,
     pub nonce: u64,
     pub proof_length: u8,
     pub level: u8,
+    pub leaf_v2: Option<LeafV2Data>,
+    pub asset_state_bump: u8,
+    pub asset_flag_bump: u8,
 }
 
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
-pub struct ClockOutData {  // 32 + 32 + 32 + 8 + 1
+pub struct ClockInBatchData {  // 4 + (174 * n) + 32 + 32
+    pub entries: Vec<ClockInEntry>,
+    pub referrer: Pubkey,
+    pub payout_recipient: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct ClockOutData {  // 32 + 32 + 32 + 8 + 1 + (1 + 65)
     pub root: Pubkey,
     pub data_hash: Pubkey,
     pub creator_hash: Pubkey,
     pub nonce: u64,
     pub proof_length: u8,
+    #[borsh(deserialize_with = "deserialize_trailing")]
+    pub leaf_v2: Option<LeafV2Data>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct ClockOutEntry {  // 32 + 32 + 32 + 8 + 1 + (1 + 65)
+    pub root: Pubkey,
+    pub data_hash: Pubkey,
+    pub creator_hash: Pubkey,
+    pub nonce: u64,
+    pub proof_length: u8,
+    pub leaf_v2: Option<LeafV2Data>,
+}
+
+// extra fields hashed into a Bubblegum V2 leaf, absent for V1 leaves
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct LeafV2Data {  // 32 + 32 + 1
+    pub collection_hash: Pubkey,
+    pub asset_data_hash: Pubkey,
+    pub flags: u8,
+}
+
+impl LeafV2Data {
+    // Bubblegum LeafSchema::V2 hash, mpl-bubblegum 1.x only ships V1
+    // version | id | owner | delegate | nonce | data hash | creator hash | collection hash | asset data hash | flags
+    pub fn leaf_hash(
+        &self,
+        id: &Pubkey,
+        owner: &Pubkey,
+        delegate: &Pubkey,
+        nonce: u64,
+        data_hash: &Pubkey,
+        creator_hash: &Pubkey,
+    ) -> [u8; 32] {
+        keccak::hashv(&[
+            &[LEAF_VERSION_V2],
+            id.as_ref(),
+            owner.as_ref(),
+            delegate.as_ref(),
+            &nonce.to_le_bytes(),
+            data_hash.as_ref(),
+            creator_hash.as_ref(),
+            self.collection_hash.as_ref(),
+            self.asset_data_hash.as_ref(),
+            &[self.flags],
+        ]).to_bytes()
+    }
+}
+
+// payloads from clients built before a trailing field existed end early, the field then reads as its default
+fn deserialize_trailing<R: borsh::io::Read, T: BorshDeserialize + Default>(
+    reader: &mut R,
+) -> borsh::io::Result<T> {
+    let mut first_byte = [0u8; 1];
+    if reader.read(&mut first_byte)? == 0 {
+        return Ok(T::default());
+    }
+    T::deserialize_reader(&mut borsh::io::Read::chain(&first_byte[..], reader))
 }
 
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
@@ -71,3 +645,157 @@ pub struct TransferPickleData {
 pub struct TransferSOLData {
     pub lamports: u64,
 }
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct UpdateBonusBracketsData {  // (6 * 4)
+    pub bonus_brackets: [BonusBracket; MAX_BONUS_BRACKETS],
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct UpdateEmissionScheduleData {  // 8 + 8 + 2 + 8
+    pub emission_start_utime: i64,
+    pub emission_epoch_seconds: i64,
+    pub emission_decay_bps: u16,
+    pub emission_floor_rate: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct UpdateEmissionCapData {  // 8 + 8
+    pub emission_cap: u64,
+    pub emission_cap_epoch_seconds: i64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct UpdateUtilizationData {  // 4 + 2 + 2
+    pub utilization_target: u32,
+    pub utilization_min_bps: u16,
+    pub utilization_max_bps: u16,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct CreateSeasonData {  // 4 + 8 + 8 + 8
+    pub season_id: u32,
+    pub start_utime: i64,
+    pub end_utime: i64,
+    pub bonus_pool: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct DistributeSeasonBonusData {  // 4
+    pub season_id: u32,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct CloseSeasonData {  // 4
+    pub season_id: u32,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct UpdateReferralData {  // 2
+    pub referral_bps: u16,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct UpdateClockInFeesData {  // 8 + 8 + 8 + 2
+    pub level_one_fee: u64,
+    pub level_seven_fee: u64,
+    pub level_thirty_fee: u64,
+    pub fee_treasury_bps: u16,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct AddTreasuryData {  // 1 + 32
+    pub treasury_type: u8,
+    pub name: [u8; 32],
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct RemoveTreasuryData {  // 1
+    pub treasury_type: u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct UpdateBurnData {  // 2
+    pub burn_bps: u16,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct UpdateVestingData {  // 1 + 8
+    pub vesting_is_enabled: u8,
+    pub vesting_seconds: i64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct SetAssetFlagData {  // 32 + 1
+    pub asset_id: Pubkey,
+    pub flag: u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct UpdateAllowlistData {  // 1
+    pub allowlist_only: u8,
+}
+
+// pay_chips_due 1 refunds the recorded chips_due as is, without burn, vesting or the payout policy
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct ForceCloseAssetStateData {  // 1 + 1
+    pub pay_chips_due: u8,
+    pub reason_code: u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct UpdatePayoutPolicyData {  // 1
+    pub payout_policy: u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct ClockOutBatchData {  // 4 + (171 * n)
+    pub entries: Vec<ClockOutEntry>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct UpdateCoreCollectionData {  // 32
+    pub core_collection: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct ClockInCoreData {  // 1 + 32 + 32 + 1 + 1
+    pub level: u8,
+    pub referrer: Pubkey,
+    pub payout_recipient: Pubkey,
+    #[borsh(deserialize_with = "deserialize_trailing")]
+    pub asset_state_bump: u8,
+    #[borsh(deserialize_with = "deserialize_trailing")]
+    pub asset_flag_bump: u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct UpdateLegacyCollectionData {  // 32
+    pub legacy_collection: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct ClockInLegacyData {  // 1 + 32 + 32 + 1 + 1
+    pub level: u8,
+    pub referrer: Pubkey,
+    pub payout_recipient: Pubkey,
+    #[borsh(deserialize_with = "deserialize_trailing")]
+    pub asset_state_bump: u8,
+    #[borsh(deserialize_with = "deserialize_trailing")]
+    pub asset_flag_bump: u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct UpdateMerkleTreeV2Data {  // 32
+    pub merkle_tree_v2: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct MigrateStateData {  // 1
+    pub account_type: u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct UpdateAssetsClockedInData {  // 4
+    pub assets_clocked_in: u32,
+}
@@ -0,0 +1,73 @@
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey,
+    pubkey::Pubkey,
+};
+use spl_token::state::Account;
+
+use crate::error::McPayError;
+
+// wallets allowed to sign the admin instructions
+pub const ADMIN_KEYS: [Pubkey; 2] = [
+    pubkey!("25hZAxGdWsP158Y8NG9eZbDwiS5bsku5UEx7ZLzVGhta"),
+    pubkey!("Aq3Nm72sY2hJScVQ89rzMKF22f3zUCbF2eioUhMeJuDj"),
+];
+
+pub fn assert_signer(account: &AccountInfo) -> ProgramResult {
+    if !account.is_signer {
+        msg!("CERROR: Account {} is not a signer", account.key);
+        return Err(McPayError::AccountNotSigner.into());
+    }
+
+    Ok(())
+}
+
+pub fn assert_admin(account: &AccountInfo) -> ProgramResult {
+    if !ADMIN_KEYS.contains(account.key) {
+        msg!("CERROR: Invalid signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    Ok(())
+}
+
+pub fn assert_writable(account: &AccountInfo) -> ProgramResult {
+    if !account.is_writable {
+        msg!("CERROR: Account {} is not writable", account.key);
+        return Err(McPayError::AccountNotWritable.into());
+    }
+
+    Ok(())
+}
+
+pub fn assert_owner(account: &AccountInfo, owner: &Pubkey, error: McPayError) -> ProgramResult {
+    if account.owner != owner {
+        msg!("CERROR: Account {} is owned by {}, expected {}", account.key, account.owner, owner);
+        return Err(error.into());
+    }
+
+    Ok(())
+}
+
+// token accounts must belong to the SPL Token program before their data is trusted
+pub fn unpack_token_account(account: &AccountInfo) -> Result<Account, ProgramError> {
+    if account.owner != &spl_token::id() {
+        msg!("CERROR: Token account {} is not owned by the SPL Token program", account.key);
+        return Err(McPayError::InvalidTokenAccountOwner.into());
+    }
+
+    Account::unpack(&account.try_borrow_data()?)
+}
+
+pub fn assert_address(account: &AccountInfo, address: &Pubkey, error: McPayError) -> ProgramResult {
+    if account.key != address {
+        msg!("CERROR: Account {} is not {}", account.key, address);
+        return Err(error.into());
+    }
+
+    Ok(())
+}
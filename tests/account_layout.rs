@@ -0,0 +1,118 @@
+// account body sizes and reads of bodies written under older layout versions
+//
+// cargo test --test account_layout
+
+use mcpay_0::state::{
+    AccountState,
+    AssetFlag,
+    AssetState,
+    ProgramState,
+    SeasonRecord,
+    SeasonState,
+    TreasuryState,
+    VestingState,
+    VestingTranche,
+    WalletState,
+};
+use solana_program::pubkey::Pubkey;
+
+fn assert_size<T: AccountState>(name: &str) {
+    assert_eq!(borsh::to_vec(&T::default()).unwrap().len(), T::SIZE, "{} SIZE", name);
+}
+
+#[test]
+fn size_matches_the_borsh_body() {
+    assert_size::<ProgramState>("ProgramState");
+    assert_size::<AssetState>("AssetState");
+    assert_size::<WalletState>("WalletState");
+    assert_size::<AssetFlag>("AssetFlag");
+    assert_size::<VestingState>("VestingState");
+    assert_size::<TreasuryState>("TreasuryState");
+    assert_size::<SeasonState>("SeasonState");
+    assert_size::<SeasonRecord>("SeasonRecord");
+}
+
+#[test]
+fn version_one_program_state_reads_without_the_appended_fields() {
+    let program_state = ProgramState {
+        is_initialized: true,
+        merkle_tree: Pubkey::new_unique(),
+        level_one_rate: 10,
+        merkle_tree_v2: Pubkey::new_unique(),
+        program_state_bump: 254,
+        total_season_pool_chips: 1_000,
+        total_referral_chips_due: 500,
+        ..ProgramState::default()
+    };
+
+    // version 1 ended at merkle_tree_v2, whatever followed it in the account is not a field yet
+    let mut body = borsh::to_vec(&program_state).unwrap();
+    body.truncate(ProgramState::SIZE - (1 + 8 + 8));
+    body.extend_from_slice(&[0xff; 64]);
+
+    let migrated = ProgramState::migrate_from(1, &body).unwrap();
+    assert_eq!(migrated, ProgramState {
+        program_state_bump: 0,
+        total_season_pool_chips: 0,
+        total_referral_chips_due: 0,
+        ..program_state.clone()
+    });
+    assert_eq!(ProgramState::migrate_from(ProgramState::VERSION, &borsh::to_vec(&program_state).unwrap()).unwrap(), program_state);
+    assert!(ProgramState::migrate_from(ProgramState::VERSION + 1, &body).is_err());
+}
+
+#[test]
+fn legacy_asset_state_reads_as_compressed() {
+    let asset_state = AssetState {
+        is_initialized: true,
+        asset_id: Pubkey::new_unique(),
+        chips_due: 42,
+        ..AssetState::default()
+    };
+
+    let mut body = borsh::to_vec(&asset_state).unwrap();
+    body.truncate(AssetState::SIZE - (1 + 1 + 1));
+
+    let migrated = AssetState::migrate_from(0, &body).unwrap();
+    assert_eq!(migrated, asset_state);
+    assert_eq!(migrated.asset_kind, 0);
+}
+
+#[test]
+fn version_two_vesting_state_becomes_the_first_tranche() {
+    let wallet = Pubkey::new_unique();
+    let mut body = Vec::new();
+    body.extend_from_slice(&borsh::to_vec(&(true, wallet, 100i64, 200i64, 1_000u64, 250u64, 75u64, 253u8)).unwrap());
+    body.extend_from_slice(&[0; 64]);
+
+    let migrated = VestingState::migrate_from(2, &body).unwrap();
+    assert!(migrated.is_initialized);
+    assert_eq!(migrated.wallet, wallet);
+    assert_eq!(migrated.unlocked_chips, 75);
+    assert_eq!(migrated.bump, 253);
+    assert_eq!(migrated.tranches[0], VestingTranche {
+        start_utime: 100,
+        end_utime: 200,
+        locked_chips: 1_000,
+        released_chips: 250,
+    });
+    assert!(migrated.tranches[1..].iter().all(|tranche| tranche.locked_chips == 0));
+
+    // version 1 had no bump yet
+    assert_eq!(VestingState::migrate_from(1, &body[..body.len() - 64 - 1]).unwrap().bump, 0);
+}
+
+#[test]
+fn version_one_asset_flag_reads_without_a_bump() {
+    let asset_flag = AssetFlag {
+        is_initialized: true,
+        asset_id: Pubkey::new_unique(),
+        flag: 2,
+        bump: 251,
+    };
+
+    let body = borsh::to_vec(&asset_flag).unwrap();
+    let migrated = AssetFlag::migrate_from(1, &body[..AssetFlag::SIZE - 1]).unwrap();
+    assert_eq!(migrated, AssetFlag { bump: 0, ..asset_flag.clone() });
+    assert_eq!(AssetFlag::migrate_from(AssetFlag::VERSION, &body).unwrap(), asset_flag);
+}
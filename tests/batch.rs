@@ -0,0 +1,69 @@
+// ClockInBatch trailing account layout: asset state, leaf delegate, asset flag and proof_length
+// proofs per entry, in entry order, then the optional leaf owner
+//
+// cargo test --test batch
+
+mod common;
+
+use common::{assert_error, Fixture, MAX_DEPTH};
+use mcpay_0::error::McPayError;
+use solana_program::{instruction::AccountMeta, pubkey::Pubkey};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::signature::Signer;
+
+const DEPTH: u8 = MAX_DEPTH as u8;
+
+async fn start(fixture: &Fixture) -> ProgramTestContext {
+    fixture.start(true, vec![fixture.wallet_state_account(true, 0)]).await
+}
+
+async fn asset_state_exists(fixture: &Fixture, context: &mut ProgramTestContext, nonce: u64) -> bool {
+    let asset_id = fixture.asset_id(nonce);
+    let (asset_state_pda, _) = fixture.pda(&[b"asset-state", asset_id.as_ref()]);
+    context.banks_client.get_account(asset_state_pda).await.unwrap().is_some()
+}
+
+#[tokio::test]
+async fn clock_in_batch_reads_each_entry_after_the_previous_ones_proofs() {
+    let fixture = Fixture::new();
+    let mut context = start(&fixture).await;
+
+    let instruction = fixture.clock_in_batch_instruction(&[(0, DEPTH), (1, DEPTH), (2, DEPTH)], true);
+    fixture.process(&mut context, instruction).await.unwrap();
+
+    for nonce in 0..3 {
+        assert!(asset_state_exists(&fixture, &mut context, nonce).await, "asset {} not clocked in", nonce);
+    }
+}
+
+// the tree fixes the proof length, an entry declaring another one is rejected on its own
+// proofs rather than shifting the accounts of the entries after it
+#[tokio::test]
+async fn clock_in_batch_with_mixed_proof_lengths() {
+    let fixture = Fixture::new();
+    let mut context = start(&fixture).await;
+
+    let instruction = fixture.clock_in_batch_instruction(&[(0, DEPTH), (1, DEPTH - 1), (2, DEPTH)], true);
+    assert_error(fixture.process(&mut context, instruction).await, McPayError::InvalidProofLength);
+
+    for nonce in 0..3 {
+        assert!(!asset_state_exists(&fixture, &mut context, nonce).await, "asset {} clocked in", nonce);
+    }
+}
+
+#[tokio::test]
+async fn clock_in_batch_accounts_must_match_the_proof_lengths() {
+    let fixture = Fixture::new();
+    let mut context = start(&fixture).await;
+
+    // one proof short
+    let mut instruction = fixture.clock_in_batch_instruction(&[(0, DEPTH), (1, DEPTH)], true);
+    instruction.accounts.pop();
+    assert_error(fixture.process(&mut context, instruction).await, McPayError::InvalidBatch);
+
+    // an account past the leaf owner
+    let mut instruction = fixture.clock_in_batch_instruction(&[(0, DEPTH), (1, DEPTH)], true);
+    instruction.accounts.push(AccountMeta::new_readonly(fixture.wallet.pubkey(), false));
+    instruction.accounts.push(AccountMeta::new_readonly(Pubkey::new_unique(), false));
+    assert_error(fixture.process(&mut context, instruction).await, McPayError::InvalidBatch);
+}
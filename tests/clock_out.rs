@@ -0,0 +1,93 @@
+// clock out settlement against the vault's unreserved balance, and denied assets clocking out unpaid
+//
+// cargo test --test clock_out
+
+mod common;
+
+use common::{assert_error, state_account, Fixture, CHIPS, LEVEL_ONE_RATE};
+use mcpay_0::{
+    error::McPayError,
+    state::{AccountState, AssetFlag, ProgramState, WalletState, ASSET_FLAG_DENY, ASSET_KIND_COMPRESSED, ASSET_KIND_CORE},
+};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::{BanksClientError, ProgramTestContext};
+
+const NONCE: u64 = 0;
+const REFERRAL_BPS: u16 = 1_000;
+const REFERRAL_CHIPS: u64 = LEVEL_ONE_RATE / 10;
+
+// a referred wallet with one compressed asset due LEVEL_ONE_RATE chips, the vault holding
+// the reserved vesting chips plus unreserved_chips
+async fn clock_out_with_referrer(unreserved_chips: u64) -> (Fixture, Pubkey, ProgramTestContext, Result<(), BanksClientError>) {
+    let fixture = Fixture::new();
+    let referrer = Pubkey::new_unique();
+    let (referrer_wallet_state_pda, referrer_wallet_state_bump) = fixture.pda(&[b"wallet-state", referrer.as_ref()]);
+    let referrer_wallet_state = WalletState {
+        is_initialized: true,
+        wallet: referrer,
+        bump: referrer_wallet_state_bump,
+        ..WalletState::default()
+    };
+
+    let program_state = ProgramState {
+        referral_bps: REFERRAL_BPS,
+        ..fixture.program_state(true)
+    };
+    let asset_id = fixture.asset_id(NONCE);
+    let extra_accounts = vec![
+        fixture.asset_state_account(&asset_id, ASSET_KIND_COMPRESSED, true, LEVEL_ONE_RATE),
+        fixture.wallet_state_with_referrer(true, 0, referrer),
+        (referrer_wallet_state_pda, state_account(&referrer_wallet_state, &fixture.program_id)),
+    ];
+    let mut context = fixture.start_with(&program_state, CHIPS + unreserved_chips, extra_accounts).await;
+
+    let instruction = fixture.clock_out_instruction(NONCE, referrer_wallet_state_pda);
+    let result = fixture.process(&mut context, instruction).await;
+    (fixture, referrer_wallet_state_pda, context, result)
+}
+
+#[tokio::test]
+async fn vault_must_cover_the_referral_credit() {
+    // enough for the payout, not for the referrer's credit on top of it
+    let (_, _, _, result) = clock_out_with_referrer(LEVEL_ONE_RATE + REFERRAL_CHIPS / 2).await;
+    assert_error(result, McPayError::InsufficientVaultPickle);
+}
+
+#[tokio::test]
+async fn vault_covering_payout_and_referral_credit_settles() {
+    let (fixture, referrer_wallet_state_pda, mut context, result) = clock_out_with_referrer(LEVEL_ONE_RATE + REFERRAL_CHIPS).await;
+    result.unwrap();
+
+    assert_eq!(fixture.token_amount(&mut context, &fixture.signer_pickle_ata).await, LEVEL_ONE_RATE);
+    let referrer_wallet_state_account = context.banks_client.get_account(referrer_wallet_state_pda).await.unwrap().unwrap();
+    let referrer_wallet_state = WalletState::unpack(&referrer_wallet_state_account.data).unwrap();
+    assert_eq!(referrer_wallet_state.referral_chips_due, REFERRAL_CHIPS);
+}
+
+// a denied core asset still clocks out, so it can be thawed, but is paid nothing
+#[tokio::test]
+async fn denied_core_asset_clocks_out_without_payout() {
+    let fixture = Fixture::new();
+    let asset = Pubkey::new_unique();
+    let (asset_state_pda, asset_state_account) = fixture.asset_state_account(&asset, ASSET_KIND_CORE, true, LEVEL_ONE_RATE);
+    let (asset_flag_pda, asset_flag_bump) = fixture.pda(&[b"asset-flag", asset.as_ref()]);
+    let asset_flag = AssetFlag {
+        is_initialized: true,
+        asset_id: asset,
+        flag: ASSET_FLAG_DENY,
+        bump: asset_flag_bump,
+    };
+    let extra_accounts = vec![
+        (asset, fixture.core_asset_account()),
+        (asset_state_pda, asset_state_account),
+        (asset_flag_pda, state_account(&asset_flag, &fixture.program_id)),
+        fixture.wallet_state_account(true, 0),
+    ];
+    let mut context = fixture.start(true, extra_accounts).await;
+
+    let instruction = fixture.clock_out_core_instruction(&asset);
+    fixture.process(&mut context, instruction).await.unwrap();
+
+    assert_eq!(fixture.token_amount(&mut context, &fixture.signer_pickle_ata).await, 0);
+    assert!(context.banks_client.get_account(asset_state_pda).await.unwrap().is_none());
+}
@@ -0,0 +1,659 @@
+// program-test fixture shared by the integration tests: the program runs natively under cargo test
+// and from the built .so under cargo test-sbf, spl-account-compression, mpl-core and Token Metadata
+// are stubbed to accept every instruction, spl-token and the associated token program are the real ones
+#![allow(dead_code)]
+
+use mcpay_0::{
+    accounts::{
+        ClockInBatchKeys,
+        ClockInCoreKeys,
+        ClockInKeys,
+        ClockInLegacyKeys,
+        ClockOutCoreKeys,
+        ClockOutKeys,
+        ClockOutLegacyKeys,
+    },
+    error::McPayError,
+    state::{
+        AccountState,
+        AssetState,
+        ClockInBatchData,
+        ClockInCoreData,
+        ClockInData,
+        ClockInEntry,
+        ClockInLegacyData,
+        ClockOutData,
+        ProgramState,
+        WalletState,
+    },
+};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    instruction::InstructionError,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_associated_token_account::get_associated_token_address;
+
+pub const CLOCK_IN: u8 = 0;
+pub const CLOCK_OUT: u8 = 1;
+pub const CLAIM_REFERRAL_REWARDS: u8 = 13;
+pub const CLAIM_VESTED: u8 = 19;
+pub const CLOCK_IN_BATCH: u8 = 24;
+pub const CLOCK_IN_CORE: u8 = 27;
+pub const CLOCK_OUT_CORE: u8 = 28;
+pub const CLOCK_IN_LEGACY: u8 = 30;
+pub const CLOCK_OUT_LEGACY: u8 = 31;
+
+pub const CHIPS: u64 = 1_000_000;
+pub const LEVEL_ONE_RATE: u64 = 100;
+
+// a depth 3 tree without a canopy takes 3 proofs
+pub const MAX_DEPTH: u32 = 3;
+pub const MAX_BUFFER_SIZE: u32 = 8;
+
+pub struct Fixture {
+    pub program_id: Pubkey,
+    pub wallet: Keypair,
+    pub pickle_mint: Pubkey,
+    pub program_state_pda: Pubkey,
+    pub mcpay_vault_pda: Pubkey,
+    pub mcpay_vault_pickle_ata: Pubkey,
+    pub signer_pickle_ata: Pubkey,
+    pub merkle_tree: Pubkey,
+    pub mcdegens_treasury: Pubkey,
+    pub core_collection: Pubkey,
+    pub legacy_collection: Pubkey,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        let program_id = mcpay_0::id();
+        let wallet = Keypair::new();
+        let pickle_mint = Pubkey::new_unique();
+        let (program_state_pda, _) = Pubkey::find_program_address(&[b"program-state"], &program_id);
+        let (mcpay_vault_pda, _) = Pubkey::find_program_address(&[b"mcpay-vault"], &program_id);
+        let mcpay_vault_pickle_ata = get_associated_token_address(&mcpay_vault_pda, &pickle_mint);
+        let signer_pickle_ata = get_associated_token_address(&wallet.pubkey(), &pickle_mint);
+
+        Self {
+            program_id,
+            wallet,
+            pickle_mint,
+            program_state_pda,
+            mcpay_vault_pda,
+            mcpay_vault_pickle_ata,
+            signer_pickle_ata,
+            merkle_tree: Pubkey::new_unique(),
+            mcdegens_treasury: Pubkey::new_unique(),
+            core_collection: Pubkey::new_unique(),
+            legacy_collection: Pubkey::new_unique(),
+        }
+    }
+
+    pub fn asset_id(&self, nonce: u64) -> Pubkey {
+        mpl_bubblegum::utils::get_asset_id(&self.merkle_tree, nonce)
+    }
+
+    pub fn pda(&self, seeds: &[&[u8]]) -> (Pubkey, u8) {
+        Pubkey::find_program_address(seeds, &self.program_id)
+    }
+
+    // stored_bump false leaves program_state_bump at 0, as on accounts written before it existed
+    pub fn program_state(&self, stored_bump: bool) -> ProgramState {
+        let (_, program_state_bump) = self.pda(&[b"program-state"]);
+        let (_, mcpay_vault_bump) = self.pda(&[b"mcpay-vault"]);
+
+        ProgramState {
+            is_initialized: true,
+            pickle_mint: self.pickle_mint,
+            mcpay_vault_pda: self.mcpay_vault_pda,
+            mcpay_vault_bump,
+            mcpay_vault_pickle_ata: self.mcpay_vault_pickle_ata,
+            total_vesting_chips: CHIPS,
+            program_state_bump: if stored_bump { program_state_bump } else { 0 },
+            clock_in_is_enabled: 1,
+            clock_out_is_enabled: 1,
+            merkle_tree: self.merkle_tree,
+            mcdegens_treasury: self.mcdegens_treasury,
+            core_collection: self.core_collection,
+            legacy_collection: self.legacy_collection,
+            level_one_rate: LEVEL_ONE_RATE,
+            ..ProgramState::default()
+        }
+    }
+
+    // the wallet's own state, with its bump when stored_bump is set
+    pub fn wallet_state_account(&self, stored_bump: bool, referral_chips_due: u64) -> (Pubkey, Account) {
+        self.wallet_state_with_referrer(stored_bump, referral_chips_due, Pubkey::default())
+    }
+
+    pub fn wallet_state_with_referrer(&self, stored_bump: bool, referral_chips_due: u64, referrer: Pubkey) -> (Pubkey, Account) {
+        let wallet = self.wallet.pubkey();
+        let (wallet_state_pda, wallet_state_bump) = self.pda(&[b"wallet-state", wallet.as_ref()]);
+        let wallet_state = WalletState {
+            is_initialized: true,
+            wallet,
+            assets_clocked_in: 0,
+            referrer,
+            referral_chips_due,
+            bump: if stored_bump { wallet_state_bump } else { 0 },
+        };
+        (wallet_state_pda, state_account(&wallet_state, &self.program_id))
+    }
+
+    // an asset the wallet clocked in at genesis on a 1 day level, due by the time the test runs
+    pub fn asset_state_account(&self, asset_id: &Pubkey, asset_kind: u8, stored_bump: bool, chips_due: u64) -> (Pubkey, Account) {
+        let wallet = self.wallet.pubkey();
+        let (asset_state_pda, asset_state_bump) = self.pda(&[b"asset-state", asset_id.as_ref()]);
+        let (_, asset_flag_bump) = self.pda(&[b"asset-flag", asset_id.as_ref()]);
+        let asset_state = AssetState {
+            is_initialized: true,
+            clock_in_wallet: wallet,
+            asset_id: *asset_id,
+            clock_in_utime: 0,
+            clock_out_utime: 0,
+            level: 1,
+            chips_due,
+            payout_recipient: Pubkey::default(),
+            rent_payer: wallet,
+            bump: if stored_bump { asset_state_bump } else { 0 },
+            asset_kind,
+            asset_flag_bump: if stored_bump { asset_flag_bump } else { 0 },
+        };
+        (asset_state_pda, state_account(&asset_state, &self.program_id))
+    }
+
+    pub async fn start(&self, stored_bump: bool, extra_accounts: Vec<(Pubkey, Account)>) -> ProgramTestContext {
+        self.start_with(&self.program_state(stored_bump), 2 * CHIPS, extra_accounts).await
+    }
+
+    pub async fn start_with(&self, program_state: &ProgramState, vault_chips: u64, extra_accounts: Vec<(Pubkey, Account)>) -> ProgramTestContext {
+        let mut program_test = ProgramTest::new("mcpay_0", self.program_id, processor!(mcpay_0::processor::Processor::process_instruction));
+        // the stubs stay native under cargo test-sbf too, there is no .so to prefer for them
+        program_test.prefer_bpf(false);
+        program_test.add_program("spl_account_compression", spl_account_compression::id(), processor!(accept));
+        program_test.add_program("mpl_core", mpl_core::ID, processor!(accept));
+        program_test.add_program("mpl_token_metadata", mpl_token_metadata::ID, processor!(accept));
+
+        program_test.add_account(self.program_state_pda, state_account(program_state, &self.program_id));
+
+        program_test.add_account(self.wallet.pubkey(), Account {
+            lamports: 1_000_000_000,
+            ..Account::default()
+        });
+        program_test.add_account(self.mcpay_vault_pda, Account {
+            lamports: 1_000_000_000,
+            ..Account::default()
+        });
+
+        let mut mint_data = vec![0u8; spl_token::state::Mint::LEN];
+        spl_token::state::Mint::pack(spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply: vault_chips,
+            decimals: 0,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        }, &mut mint_data).unwrap();
+        program_test.add_account(self.pickle_mint, Account {
+            lamports: Rent::default().minimum_balance(mint_data.len()),
+            data: mint_data,
+            owner: spl_token::id(),
+            ..Account::default()
+        });
+        program_test.add_account(self.mcpay_vault_pickle_ata, token_account(&self.pickle_mint, &self.mcpay_vault_pda, vault_chips));
+        program_test.add_account(self.signer_pickle_ata, token_account(&self.pickle_mint, &self.wallet.pubkey(), 0));
+
+        // spl-account-compression header: version 1, max buffer size, max depth, then the tree
+        let path_size = 32 * MAX_DEPTH as usize + 40;
+        let mut merkle_tree_data = vec![0u8; 56 + MAX_BUFFER_SIZE as usize * path_size + 24 + path_size];
+        merkle_tree_data[0] = 1;
+        merkle_tree_data[2..6].copy_from_slice(&MAX_BUFFER_SIZE.to_le_bytes());
+        merkle_tree_data[6..10].copy_from_slice(&MAX_DEPTH.to_le_bytes());
+        program_test.add_account(self.merkle_tree, Account {
+            lamports: Rent::default().minimum_balance(merkle_tree_data.len()),
+            data: merkle_tree_data,
+            owner: spl_account_compression::id(),
+            ..Account::default()
+        });
+
+        for (address, account) in extra_accounts {
+            program_test.add_account(address, account);
+        }
+
+        program_test.start_with_context().await
+    }
+
+    pub fn transaction(&self, context: &ProgramTestContext, instruction: Instruction) -> Transaction {
+        Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.wallet.pubkey()),
+            &[&self.wallet],
+            context.last_blockhash,
+        )
+    }
+
+    pub async fn process(&self, context: &mut ProgramTestContext, instruction: Instruction) -> Result<(), BanksClientError> {
+        let transaction = self.transaction(context, instruction);
+        context.banks_client.process_transaction(transaction).await
+    }
+
+    pub async fn units_consumed(&self, context: &mut ProgramTestContext, instruction: Instruction) -> u64 {
+        let transaction = self.transaction(context, instruction);
+        let simulation = context.banks_client.simulate_transaction(transaction).await.unwrap();
+        simulation.result.unwrap().unwrap();
+        simulation.simulation_details.unwrap().units_consumed
+    }
+
+    pub async fn token_amount(&self, context: &mut ProgramTestContext, token_account: &Pubkey) -> u64 {
+        let account = context.banks_client.get_account(*token_account).await.unwrap().unwrap();
+        spl_token::state::Account::unpack(&account.data).unwrap().amount
+    }
+
+    // a Core asset in the configured collection, owned by the wallet
+    pub fn core_asset_account(&self) -> Account {
+        let mut data = vec![1]; // Key::AssetV1
+        data.extend_from_slice(self.wallet.pubkey().as_ref());
+        data.push(2); // UpdateAuthority::Collection
+        data.extend_from_slice(self.core_collection.as_ref());
+        data.extend(borsh::to_vec(&("McDegen".to_string(), "https://example.com".to_string(), None::<u64>)).unwrap());
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: mpl_core::ID,
+            ..Account::default()
+        }
+    }
+
+    // a verified Token Metadata nft in the configured collection held by the wallet,
+    // returns the mint, its token account, metadata and edition
+    pub fn legacy_nft_accounts(&self, delegate: Option<Pubkey>) -> Vec<(Pubkey, Account)> {
+        let mint = Pubkey::new_unique();
+        let token_account_address = get_associated_token_address(&self.wallet.pubkey(), &mint);
+        let (metadata, _) = mpl_token_metadata::accounts::Metadata::find_pda(&mint);
+        let (edition, _) = mpl_token_metadata::accounts::MasterEdition::find_pda(&mint);
+
+        let mut mint_data = vec![0u8; spl_token::state::Mint::LEN];
+        spl_token::state::Mint::pack(spl_token::state::Mint {
+            mint_authority: COption::Some(edition),
+            supply: 1,
+            decimals: 0,
+            is_initialized: true,
+            freeze_authority: COption::Some(edition),
+        }, &mut mint_data).unwrap();
+
+        let mut token_account_data = token_account(&mint, &self.wallet.pubkey(), 1);
+        if let Some(delegate) = delegate {
+            let mut token_account_state = spl_token::state::Account::unpack(&token_account_data.data).unwrap();
+            token_account_state.delegate = COption::Some(delegate);
+            token_account_state.delegated_amount = 1;
+            spl_token::state::Account::pack(token_account_state, &mut token_account_data.data).unwrap();
+        }
+
+        // Key::MetadataV1, update authority, mint, name, symbol, uri, seller fee, no creators,
+        // primary sale, mutable, no edition nonce, NonFungible, the verified collection, no uses,
+        // collection details or programmable config
+        let mut metadata_data = vec![4];
+        metadata_data.extend_from_slice(self.wallet.pubkey().as_ref());
+        metadata_data.extend_from_slice(mint.as_ref());
+        metadata_data.extend(borsh::to_vec(&("McDegen".to_string(), "MCD".to_string(), "https://example.com".to_string(), 0u16)).unwrap());
+        metadata_data.extend_from_slice(&[0, 0, 1, 0, 1, 0, 1, 1]);
+        metadata_data.extend_from_slice(self.legacy_collection.as_ref());
+        metadata_data.extend_from_slice(&[0, 0, 0]);
+
+        vec![
+            (mint, Account {
+                lamports: Rent::default().minimum_balance(mint_data.len()),
+                data: mint_data,
+                owner: spl_token::id(),
+                ..Account::default()
+            }),
+            (token_account_address, token_account_data),
+            (metadata, Account {
+                lamports: Rent::default().minimum_balance(metadata_data.len()),
+                data: metadata_data,
+                owner: mpl_token_metadata::ID,
+                ..Account::default()
+            }),
+            (edition, Account {
+                lamports: Rent::default().minimum_balance(0),
+                owner: mpl_token_metadata::ID,
+                ..Account::default()
+            }),
+        ]
+    }
+}
+
+// instructions as a client builds them, with the canonical bumps when stored_bump is set
+// and 0 (the program searches) otherwise
+impl Fixture {
+    fn bump(&self, stored_bump: bool, seeds: &[&[u8]]) -> u8 {
+        if stored_bump { self.pda(seeds).1 } else { 0 }
+    }
+
+    pub fn clock_in_instruction(&self, nonce: u64, stored_bump: bool) -> Instruction {
+        let wallet = self.wallet.pubkey();
+        let asset_id = self.asset_id(nonce);
+        let asset_state_pda = self.pda(&[b"asset-state", asset_id.as_ref()]).0;
+        let asset_flag_pda = self.pda(&[b"asset-flag", asset_id.as_ref()]).0;
+
+        Instruction {
+            program_id: self.program_id,
+            accounts: ClockInKeys {
+                signer: wallet,
+                program_state_pda: self.program_state_pda,
+                asset_state_pda,
+                leaf_delegate: wallet,
+                merkle_tree: self.merkle_tree,
+                spl_account_compression_program_id: spl_account_compression::id(),
+                system_program_id: system_program::id(),
+                wallet_state_pda: self.pda(&[b"wallet-state", wallet.as_ref()]).0,
+                referrer_wallet_state_pda: Pubkey::new_unique(),
+                mcpay_vault_pda: self.mcpay_vault_pda,
+                mcdegens_treasury: self.mcdegens_treasury,
+                asset_flag_pda,
+                rent_payer: wallet,
+            }.to_account_metas(&proof_metas(MAX_DEPTH as u8)),
+            data: instruction_data(CLOCK_IN, &ClockInData {
+                root: Pubkey::new_unique(),
+                data_hash: Pubkey::new_unique(),
+                creator_hash: Pubkey::new_unique(),
+                nonce,
+                proof_length: MAX_DEPTH as u8,
+                level: 1,
+                referrer: Pubkey::default(),
+                payout_recipient: Pubkey::default(),
+                leaf_v2: None,
+                asset_state_bump: self.bump(stored_bump, &[b"asset-state", asset_id.as_ref()]),
+                asset_flag_bump: self.bump(stored_bump, &[b"asset-flag", asset_id.as_ref()]),
+            }),
+        }
+    }
+
+    // one entry per (nonce, proof length), each entry's accounts followed by its own proofs
+    pub fn clock_in_batch_instruction(&self, entries: &[(u64, u8)], stored_bump: bool) -> Instruction {
+        let wallet = self.wallet.pubkey();
+        let mut entry_metas = vec![];
+        let mut clock_in_entries = vec![];
+        for &(nonce, proof_length) in entries {
+            let asset_id = self.asset_id(nonce);
+            entry_metas.push(AccountMeta::new(self.pda(&[b"asset-state", asset_id.as_ref()]).0, false));
+            entry_metas.push(AccountMeta::new_readonly(wallet, false));
+            entry_metas.push(AccountMeta::new_readonly(self.pda(&[b"asset-flag", asset_id.as_ref()]).0, false));
+            entry_metas.extend(proof_metas(proof_length));
+            clock_in_entries.push(ClockInEntry {
+                root: Pubkey::new_unique(),
+                data_hash: Pubkey::new_unique(),
+                creator_hash: Pubkey::new_unique(),
+                nonce,
+                proof_length,
+                level: 1,
+                leaf_v2: None,
+                asset_state_bump: self.bump(stored_bump, &[b"asset-state", asset_id.as_ref()]),
+                asset_flag_bump: self.bump(stored_bump, &[b"asset-flag", asset_id.as_ref()]),
+            });
+        }
+
+        Instruction {
+            program_id: self.program_id,
+            accounts: ClockInBatchKeys {
+                signer: wallet,
+                program_state_pda: self.program_state_pda,
+                merkle_tree: self.merkle_tree,
+                spl_account_compression_program_id: spl_account_compression::id(),
+                system_program_id: system_program::id(),
+                wallet_state_pda: self.pda(&[b"wallet-state", wallet.as_ref()]).0,
+                referrer_wallet_state_pda: Pubkey::new_unique(),
+                mcpay_vault_pda: self.mcpay_vault_pda,
+                mcdegens_treasury: self.mcdegens_treasury,
+                rent_payer: wallet,
+            }.to_account_metas(&entry_metas),
+            data: instruction_data(CLOCK_IN_BATCH, &ClockInBatchData {
+                entries: clock_in_entries,
+                referrer: Pubkey::default(),
+                payout_recipient: Pubkey::default(),
+            }),
+        }
+    }
+
+    pub fn clock_out_instruction(&self, nonce: u64, referrer_wallet_state_pda: Pubkey) -> Instruction {
+        let wallet = self.wallet.pubkey();
+        let asset_id = self.asset_id(nonce);
+
+        Instruction {
+            program_id: self.program_id,
+            accounts: ClockOutKeys {
+                signer: wallet,
+                program_state_pda: self.program_state_pda,
+                asset_state_pda: self.pda(&[b"asset-state", asset_id.as_ref()]).0,
+                leaf_delegate: wallet,
+                merkle_tree: self.merkle_tree,
+                spl_account_compression_program_id: spl_account_compression::id(),
+                spl_token_program_id: spl_token::id(),
+                mcpay_vault_pda: self.mcpay_vault_pda,
+                mcpay_vault_pickle_ata: self.mcpay_vault_pickle_ata,
+                payout_pickle_ata: self.signer_pickle_ata,
+                clock_in_wallet: wallet,
+                wallet_state_pda: self.pda(&[b"wallet-state", wallet.as_ref()]).0,
+                season_pda: Pubkey::new_unique(),
+                season_record_pda: Pubkey::new_unique(),
+                system_program_id: system_program::id(),
+                referrer_wallet_state_pda,
+                pickle_mint: self.pickle_mint,
+                vesting_pda: self.pda(&[b"vesting", wallet.as_ref()]).0,
+                asset_flag_pda: self.pda(&[b"asset-flag", asset_id.as_ref()]).0,
+                payout_wallet: wallet,
+                spl_associated_token_program_id: spl_associated_token_account::id(),
+                rent_payer: wallet,
+            }.to_account_metas(&proof_metas(MAX_DEPTH as u8)),
+            data: instruction_data(CLOCK_OUT, &ClockOutData {
+                root: Pubkey::new_unique(),
+                data_hash: Pubkey::new_unique(),
+                creator_hash: Pubkey::new_unique(),
+                nonce,
+                proof_length: MAX_DEPTH as u8,
+                leaf_v2: None,
+            }),
+        }
+    }
+
+    pub fn clock_in_core_instruction(&self, asset: &Pubkey, stored_bump: bool) -> Instruction {
+        let wallet = self.wallet.pubkey();
+
+        Instruction {
+            program_id: self.program_id,
+            accounts: ClockInCoreKeys {
+                signer: wallet,
+                program_state_pda: self.program_state_pda,
+                asset_state_pda: self.pda(&[b"asset-state", asset.as_ref()]).0,
+                asset: *asset,
+                collection: self.core_collection,
+                mpl_core_program_id: mpl_core::ID,
+                system_program_id: system_program::id(),
+                wallet_state_pda: self.pda(&[b"wallet-state", wallet.as_ref()]).0,
+                referrer_wallet_state_pda: Pubkey::new_unique(),
+                mcpay_vault_pda: self.mcpay_vault_pda,
+                mcdegens_treasury: self.mcdegens_treasury,
+                asset_flag_pda: self.pda(&[b"asset-flag", asset.as_ref()]).0,
+                rent_payer: wallet,
+            }.to_account_metas(&[]),
+            data: instruction_data(CLOCK_IN_CORE, &ClockInCoreData {
+                level: 1,
+                referrer: Pubkey::default(),
+                payout_recipient: Pubkey::default(),
+                asset_state_bump: self.bump(stored_bump, &[b"asset-state", asset.as_ref()]),
+                asset_flag_bump: self.bump(stored_bump, &[b"asset-flag", asset.as_ref()]),
+            }),
+        }
+    }
+
+    pub fn clock_out_core_instruction(&self, asset: &Pubkey) -> Instruction {
+        let wallet = self.wallet.pubkey();
+
+        Instruction {
+            program_id: self.program_id,
+            accounts: ClockOutCoreKeys {
+                signer: wallet,
+                program_state_pda: self.program_state_pda,
+                asset_state_pda: self.pda(&[b"asset-state", asset.as_ref()]).0,
+                asset: *asset,
+                collection: self.core_collection,
+                mpl_core_program_id: mpl_core::ID,
+                spl_token_program_id: spl_token::id(),
+                mcpay_vault_pda: self.mcpay_vault_pda,
+                mcpay_vault_pickle_ata: self.mcpay_vault_pickle_ata,
+                payout_pickle_ata: self.signer_pickle_ata,
+                clock_in_wallet: wallet,
+                wallet_state_pda: self.pda(&[b"wallet-state", wallet.as_ref()]).0,
+                season_pda: Pubkey::new_unique(),
+                season_record_pda: Pubkey::new_unique(),
+                system_program_id: system_program::id(),
+                referrer_wallet_state_pda: Pubkey::new_unique(),
+                pickle_mint: self.pickle_mint,
+                vesting_pda: self.pda(&[b"vesting", wallet.as_ref()]).0,
+                asset_flag_pda: self.pda(&[b"asset-flag", asset.as_ref()]).0,
+                payout_wallet: wallet,
+                spl_associated_token_program_id: spl_associated_token_account::id(),
+                rent_payer: wallet,
+            }.to_account_metas(&[]),
+            data: vec![CLOCK_OUT_CORE],
+        }
+    }
+
+    // legacy_nft is the mint, token account, metadata and edition from legacy_nft_accounts
+    pub fn clock_in_legacy_instruction(&self, legacy_nft: &[Pubkey], stored_bump: bool) -> Instruction {
+        let wallet = self.wallet.pubkey();
+        let mint = legacy_nft[0];
+
+        Instruction {
+            program_id: self.program_id,
+            accounts: ClockInLegacyKeys {
+                signer: wallet,
+                program_state_pda: self.program_state_pda,
+                asset_state_pda: self.pda(&[b"asset-state", mint.as_ref()]).0,
+                mint,
+                token_account: legacy_nft[1],
+                metadata_pda: legacy_nft[2],
+                edition_pda: legacy_nft[3],
+                token_metadata_program_id: mpl_token_metadata::ID,
+                spl_token_program_id: spl_token::id(),
+                system_program_id: system_program::id(),
+                wallet_state_pda: self.pda(&[b"wallet-state", wallet.as_ref()]).0,
+                referrer_wallet_state_pda: Pubkey::new_unique(),
+                mcpay_vault_pda: self.mcpay_vault_pda,
+                mcdegens_treasury: self.mcdegens_treasury,
+                asset_flag_pda: self.pda(&[b"asset-flag", mint.as_ref()]).0,
+                rent_payer: wallet,
+            }.to_account_metas(&[]),
+            data: instruction_data(CLOCK_IN_LEGACY, &ClockInLegacyData {
+                level: 1,
+                referrer: Pubkey::default(),
+                payout_recipient: Pubkey::default(),
+                asset_state_bump: self.bump(stored_bump, &[b"asset-state", mint.as_ref()]),
+                asset_flag_bump: self.bump(stored_bump, &[b"asset-flag", mint.as_ref()]),
+            }),
+        }
+    }
+
+    pub fn clock_out_legacy_instruction(&self, legacy_nft: &[Pubkey]) -> Instruction {
+        let wallet = self.wallet.pubkey();
+        let mint = legacy_nft[0];
+
+        Instruction {
+            program_id: self.program_id,
+            accounts: ClockOutLegacyKeys {
+                signer: wallet,
+                program_state_pda: self.program_state_pda,
+                asset_state_pda: self.pda(&[b"asset-state", mint.as_ref()]).0,
+                mint,
+                token_account: legacy_nft[1],
+                metadata_pda: legacy_nft[2],
+                edition_pda: legacy_nft[3],
+                token_metadata_program_id: mpl_token_metadata::ID,
+                spl_token_program_id: spl_token::id(),
+                mcpay_vault_pda: self.mcpay_vault_pda,
+                mcpay_vault_pickle_ata: self.mcpay_vault_pickle_ata,
+                payout_pickle_ata: self.signer_pickle_ata,
+                clock_in_wallet: wallet,
+                wallet_state_pda: self.pda(&[b"wallet-state", wallet.as_ref()]).0,
+                season_pda: Pubkey::new_unique(),
+                season_record_pda: Pubkey::new_unique(),
+                system_program_id: system_program::id(),
+                referrer_wallet_state_pda: Pubkey::new_unique(),
+                pickle_mint: self.pickle_mint,
+                vesting_pda: self.pda(&[b"vesting", wallet.as_ref()]).0,
+                asset_flag_pda: self.pda(&[b"asset-flag", mint.as_ref()]).0,
+                payout_wallet: wallet,
+                spl_associated_token_program_id: spl_associated_token_account::id(),
+                rent_payer: wallet,
+            }.to_account_metas(&[]),
+            data: vec![CLOCK_OUT_LEGACY],
+        }
+    }
+}
+
+// stands in for the programs the clock in and out paths invoke, the leaf hash itself is covered by tests/verify_leaf.rs
+fn accept(_program_id: &Pubkey, _accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    Ok(())
+}
+
+pub fn assert_error(result: Result<(), BanksClientError>, error: McPayError) {
+    match result {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(_, InstructionError::Custom(code)))) => {
+            assert_eq!(code, error as u32, "expected {:?}", error);
+        }
+        result => panic!("expected {:?}, got {:?}", error, result),
+    }
+}
+
+pub fn proof_metas(proof_length: u8) -> Vec<AccountMeta> {
+    (0..proof_length).map(|_| AccountMeta::new_readonly(Pubkey::new_unique(), false)).collect()
+}
+
+pub fn instruction_data<T: borsh::BorshSerialize>(tag: u8, data: &T) -> Vec<u8> {
+    let mut instruction_data = vec![tag];
+    instruction_data.extend(borsh::to_vec(data).unwrap());
+    instruction_data
+}
+
+pub fn state_account<T: AccountState>(state: &T, owner: &Pubkey) -> Account {
+    let mut data = vec![0u8; T::LEN];
+    state.pack(&mut data).unwrap();
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: *owner,
+        ..Account::default()
+    }
+}
+
+pub fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut data = vec![0u8; spl_token::state::Account::LEN];
+    spl_token::state::Account::pack(spl_token::state::Account {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        delegate: COption::None,
+        state: spl_token::state::AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    }, &mut data).unwrap();
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: spl_token::id(),
+        ..Account::default()
+    }
+}
@@ -0,0 +1,257 @@
+// compute units of the clock in and out hot paths (compressed, batch, Core and legacy) and the claims,
+// each run with legacy accounts (no stored bumps, find_program_address) and current ones
+// (stored bumps, create_program_address)
+//
+// plain cargo test runs the program natively, where nothing is metered, so there the paths only have
+// to go through; the comparison and the budgets apply to the built program:
+// cargo build-sbf && cargo test-sbf --test compute_units -- --nocapture
+
+mod common;
+
+use common::{
+    state_account,
+    Fixture,
+    CHIPS,
+    CLAIM_REFERRAL_REWARDS,
+    CLAIM_VESTED,
+    LEVEL_ONE_RATE,
+    MAX_DEPTH,
+};
+use mcpay_0::{
+    accounts::{ClaimReferralRewardsKeys, ClaimVestedKeys},
+    state::{
+        VestingState,
+        VestingTranche,
+        ASSET_KIND_COMPRESSED,
+        ASSET_KIND_CORE,
+        ASSET_KIND_LEGACY,
+    },
+};
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use solana_sdk::signature::Signer;
+
+// ceilings for the stored bump paths, to be tightened to the cargo test-sbf numbers plus headroom
+const CLOCK_IN_BUDGET: u64 = 80_000;
+const CLOCK_OUT_BUDGET: u64 = 90_000;
+const CLOCK_IN_BATCH_BUDGET: u64 = 160_000;
+const CLOCK_IN_CORE_BUDGET: u64 = 60_000;
+const CLOCK_OUT_CORE_BUDGET: u64 = 80_000;
+const CLOCK_IN_LEGACY_BUDGET: u64 = 70_000;
+const CLOCK_OUT_LEGACY_BUDGET: u64 = 90_000;
+
+const NONCE: u64 = 0;
+
+// set by cargo test-sbf, which loads the built program in place of the native processor
+fn metered() -> bool {
+    std::env::var_os("SBF_OUT_DIR").is_some() || std::env::var_os("BPF_OUT_DIR").is_some()
+}
+
+fn report(name: &str, legacy: u64, stored: u64) {
+    if !metered() {
+        println!("{:<24} ran natively, compute units are only metered under cargo test-sbf", name);
+        return;
+    }
+    println!("{:<24} find {:>7} CU  stored bump {:>7} CU  saved {:>6} CU", name, legacy, stored, legacy.saturating_sub(stored));
+    assert!(stored < legacy, "{} did not get cheaper with the stored bump", name);
+}
+
+fn assert_budget(name: &str, units: u64, budget: u64) {
+    if metered() {
+        assert!(units <= budget, "{} used {} CU, over its {} CU budget", name, units, budget);
+    }
+}
+
+// runs the instruction once against legacy accounts and once against current ones
+async fn measure<F>(fixture: &Fixture, accounts: F, instruction: impl Fn(bool) -> Instruction) -> [u64; 2]
+where
+    F: Fn(bool) -> Vec<(Pubkey, solana_sdk::account::Account)>,
+{
+    let mut units = [0u64; 2];
+    for (i, stored_bump) in [false, true].into_iter().enumerate() {
+        let mut context = fixture.start(stored_bump, accounts(stored_bump)).await;
+        units[i] = fixture.units_consumed(&mut context, instruction(stored_bump)).await;
+    }
+    units
+}
+
+#[tokio::test]
+async fn clock_in() {
+    let fixture = Fixture::new();
+    let units = measure(
+        &fixture,
+        |stored_bump| vec![fixture.wallet_state_account(stored_bump, 0)],
+        |stored_bump| fixture.clock_in_instruction(NONCE, stored_bump),
+    ).await;
+    report("ClockIn", units[0], units[1]);
+    assert_budget("ClockIn", units[1], CLOCK_IN_BUDGET);
+}
+
+#[tokio::test]
+async fn clock_in_batch() {
+    let fixture = Fixture::new();
+    let entries = [(NONCE, MAX_DEPTH as u8), (NONCE + 1, MAX_DEPTH as u8)];
+    let units = measure(
+        &fixture,
+        |stored_bump| vec![fixture.wallet_state_account(stored_bump, 0)],
+        |stored_bump| fixture.clock_in_batch_instruction(&entries, stored_bump),
+    ).await;
+    report("ClockInBatch (2)", units[0], units[1]);
+    assert_budget("ClockInBatch (2)", units[1], CLOCK_IN_BATCH_BUDGET);
+}
+
+#[tokio::test]
+async fn clock_out() {
+    let fixture = Fixture::new();
+    let asset_id = fixture.asset_id(NONCE);
+    let units = measure(
+        &fixture,
+        |stored_bump| vec![
+            fixture.asset_state_account(&asset_id, ASSET_KIND_COMPRESSED, stored_bump, LEVEL_ONE_RATE),
+            fixture.wallet_state_account(stored_bump, 0),
+        ],
+        |_| fixture.clock_out_instruction(NONCE, Pubkey::new_unique()),
+    ).await;
+    report("ClockOut", units[0], units[1]);
+    assert_budget("ClockOut", units[1], CLOCK_OUT_BUDGET);
+}
+
+#[tokio::test]
+async fn clock_in_core() {
+    let fixture = Fixture::new();
+    let asset = Pubkey::new_unique();
+    let units = measure(
+        &fixture,
+        |stored_bump| vec![
+            (asset, fixture.core_asset_account()),
+            fixture.wallet_state_account(stored_bump, 0),
+        ],
+        |stored_bump| fixture.clock_in_core_instruction(&asset, stored_bump),
+    ).await;
+    report("ClockInCore", units[0], units[1]);
+    assert_budget("ClockInCore", units[1], CLOCK_IN_CORE_BUDGET);
+}
+
+#[tokio::test]
+async fn clock_out_core() {
+    let fixture = Fixture::new();
+    let asset = Pubkey::new_unique();
+    let units = measure(
+        &fixture,
+        |stored_bump| vec![
+            (asset, fixture.core_asset_account()),
+            fixture.asset_state_account(&asset, ASSET_KIND_CORE, stored_bump, LEVEL_ONE_RATE),
+            fixture.wallet_state_account(stored_bump, 0),
+        ],
+        |_| fixture.clock_out_core_instruction(&asset),
+    ).await;
+    report("ClockOutCore", units[0], units[1]);
+    assert_budget("ClockOutCore", units[1], CLOCK_OUT_CORE_BUDGET);
+}
+
+#[tokio::test]
+async fn clock_in_legacy() {
+    let fixture = Fixture::new();
+    let legacy_nft = fixture.legacy_nft_accounts(None);
+    let legacy_nft_keys: Vec<Pubkey> = legacy_nft.iter().map(|(address, _)| *address).collect();
+    let units = measure(
+        &fixture,
+        |stored_bump| {
+            let mut accounts = legacy_nft.clone();
+            accounts.push(fixture.wallet_state_account(stored_bump, 0));
+            accounts
+        },
+        |stored_bump| fixture.clock_in_legacy_instruction(&legacy_nft_keys, stored_bump),
+    ).await;
+    report("ClockInLegacy", units[0], units[1]);
+    assert_budget("ClockInLegacy", units[1], CLOCK_IN_LEGACY_BUDGET);
+}
+
+#[tokio::test]
+async fn clock_out_legacy() {
+    let fixture = Fixture::new();
+    let legacy_nft = fixture.legacy_nft_accounts(Some(fixture.mcpay_vault_pda));
+    let legacy_nft_keys: Vec<Pubkey> = legacy_nft.iter().map(|(address, _)| *address).collect();
+    let units = measure(
+        &fixture,
+        |stored_bump| {
+            let mut accounts = legacy_nft.clone();
+            accounts.push(fixture.asset_state_account(&legacy_nft_keys[0], ASSET_KIND_LEGACY, stored_bump, LEVEL_ONE_RATE));
+            accounts.push(fixture.wallet_state_account(stored_bump, 0));
+            accounts
+        },
+        |_| fixture.clock_out_legacy_instruction(&legacy_nft_keys),
+    ).await;
+    report("ClockOutLegacy", units[0], units[1]);
+    assert_budget("ClockOutLegacy", units[1], CLOCK_OUT_LEGACY_BUDGET);
+}
+
+#[tokio::test]
+async fn claim_referral_rewards() {
+    let fixture = Fixture::new();
+    let wallet = fixture.wallet.pubkey();
+    let (wallet_state_pda, _) = fixture.pda(&[b"wallet-state", wallet.as_ref()]);
+
+    let instruction = Instruction {
+        program_id: fixture.program_id,
+        accounts: ClaimReferralRewardsKeys {
+            signer: wallet,
+            program_state_pda: fixture.program_state_pda,
+            wallet_state_pda,
+            signer_pickle_ata: fixture.signer_pickle_ata,
+            spl_token_program_id: spl_token::id(),
+            mcpay_vault_pda: fixture.mcpay_vault_pda,
+            mcpay_vault_pickle_ata: fixture.mcpay_vault_pickle_ata,
+        }.to_account_metas(&[]),
+        data: vec![CLAIM_REFERRAL_REWARDS],
+    };
+
+    let units = measure(
+        &fixture,
+        |stored_bump| vec![fixture.wallet_state_account(stored_bump, CHIPS)],
+        |_| instruction.clone(),
+    ).await;
+    report("ClaimReferralRewards", units[0], units[1]);
+}
+
+#[tokio::test]
+async fn claim_vested() {
+    let fixture = Fixture::new();
+    let wallet = fixture.wallet.pubkey();
+    let (vesting_pda, vesting_bump) = fixture.pda(&[b"vesting", wallet.as_ref()]);
+
+    let instruction = Instruction {
+        program_id: fixture.program_id,
+        accounts: ClaimVestedKeys {
+            signer: wallet,
+            program_state_pda: fixture.program_state_pda,
+            vesting_pda,
+            signer_pickle_ata: fixture.signer_pickle_ata,
+            spl_token_program_id: spl_token::id(),
+            mcpay_vault_pda: fixture.mcpay_vault_pda,
+            mcpay_vault_pickle_ata: fixture.mcpay_vault_pickle_ata,
+        }.to_account_metas(&[]),
+        data: vec![CLAIM_VESTED],
+    };
+
+    let units = measure(
+        &fixture,
+        |stored_bump| {
+            let mut vesting_state = VestingState {
+                is_initialized: true,
+                wallet,
+                unlocked_chips: 0,
+                bump: if stored_bump { vesting_bump } else { 0 },
+                ..VestingState::default()
+            };
+            vesting_state.tranches[0] = VestingTranche {
+                start_utime: 0,
+                end_utime: 0,
+                locked_chips: CHIPS,
+                released_chips: 0,
+            };
+            vec![(vesting_pda, state_account(&vesting_state, &fixture.program_id))]
+        },
+        |_| instruction.clone(),
+    ).await;
+    report("ClaimVested", units[0], units[1]);
+}
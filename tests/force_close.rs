@@ -0,0 +1,179 @@
+// ForceCloseAssetState with pay_chips_due 1 refunds the recorded chips_due outside the clock out rules
+//
+// the admin keys can't sign a program-test transaction, so the processor is called directly with the
+// invokes it makes recorded instead of run
+//
+// cargo test --test force_close
+
+mod common;
+
+use std::{cell::RefCell, sync::Once};
+
+use common::{state_account, token_account, Fixture, CHIPS, LEVEL_ONE_RATE};
+use mcpay_0::{
+    processor::Processor,
+    state::{
+        AccountState,
+        AssetState,
+        ForceCloseAssetStateData,
+        ProgramState,
+        ASSET_KIND_COMPRESSED,
+        PAYOUT_RECIPIENT,
+    },
+    validation::ADMIN_KEYS,
+};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    program_error::ProgramError,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+};
+use solana_sdk::{account::Account, signature::Signer};
+use spl_associated_token_account::get_associated_token_address;
+
+const FORCE_CLOSE_ASSET_STATE: u8 = 22;
+const NONCE: u64 = 0;
+
+thread_local! {
+    static INVOKED: RefCell<Vec<Instruction>> = const { RefCell::new(vec![]) };
+}
+
+struct RecordInvokes;
+
+impl SyscallStubs for RecordInvokes {
+    fn sol_invoke_signed(&self, instruction: &Instruction, _account_infos: &[AccountInfo], _signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+        INVOKED.with(|invoked| invoked.borrow_mut().push(instruction.clone()));
+        Ok(())
+    }
+}
+
+struct ForceClose {
+    accounts: Vec<(Pubkey, bool, bool, Account)>,
+    asset_state_pda: Pubkey,
+    program_state_pda: Pubkey,
+}
+
+impl ForceClose {
+    // a compressed asset of the fixture wallet due LEVEL_ONE_RATE chips, with burn, vesting and the
+    // recipient payout policy all turned on
+    fn new(fixture: &Fixture, signer: Pubkey) -> Self {
+        let wallet = fixture.wallet.pubkey();
+        let program_state = ProgramState {
+            burn_bps: 5_000,
+            vesting_is_enabled: 1,
+            vesting_seconds: 86_400,
+            payout_policy: PAYOUT_RECIPIENT,
+            assets_clocked_in: 1,
+            ..fixture.program_state(true)
+        };
+        let asset_id = fixture.asset_id(NONCE);
+        let (asset_state_pda, asset_state_account) = fixture.asset_state_account(&asset_id, ASSET_KIND_COMPRESSED, true, LEVEL_ONE_RATE);
+        let (wallet_state_pda, wallet_state_account) = fixture.wallet_state_account(true, 0);
+
+        // in ForceCloseAssetStateAccounts order: key, signer, writable, account
+        let accounts = vec![
+            (signer, true, false, Account::default()),
+            (fixture.program_state_pda, false, true, state_account(&program_state, &fixture.program_id)),
+            (asset_state_pda, false, true, asset_state_account),
+            (wallet, false, false, Account::default()),
+            (wallet_state_pda, false, true, wallet_state_account),
+            (spl_token::id(), false, false, Account::default()),
+            (fixture.mcpay_vault_pda, false, false, Account::default()),
+            (fixture.mcpay_vault_pickle_ata, false, true, token_account(&fixture.pickle_mint, &fixture.mcpay_vault_pda, 2 * CHIPS)),
+            (get_associated_token_address(&wallet, &fixture.pickle_mint), false, true, Account::default()),
+            (wallet, false, true, Account::default()),
+        ];
+        Self {
+            accounts,
+            asset_state_pda,
+            program_state_pda: fixture.program_state_pda,
+        }
+    }
+
+    // runs the instruction, returning the instructions it invoked
+    fn process(&mut self, program_id: &Pubkey, pay_chips_due: u8) -> Result<Vec<Instruction>, ProgramError> {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(RecordInvokes));
+        });
+        INVOKED.with(|invoked| invoked.borrow_mut().clear());
+
+        let data = common::instruction_data(FORCE_CLOSE_ASSET_STATE, &ForceCloseAssetStateData {
+            pay_chips_due,
+            reason_code: 1,
+        });
+        let account_infos: Vec<AccountInfo> = self.accounts.iter_mut()
+            .map(|(key, is_signer, is_writable, account)| AccountInfo::new(
+                key,
+                *is_signer,
+                *is_writable,
+                &mut account.lamports,
+                &mut account.data,
+                &account.owner,
+                false,
+                0,
+            ))
+            .collect();
+        Processor::process_instruction(program_id, &account_infos, &data)?;
+
+        Ok(INVOKED.with(|invoked| invoked.take()))
+    }
+
+    fn account(&self, address: &Pubkey) -> &Account {
+        &self.accounts.iter().find(|(key, ..)| key == address).unwrap().3
+    }
+}
+
+#[test]
+fn paid_force_close_refunds_chips_due_without_burn_vesting_or_payout_policy() {
+    let fixture = Fixture::new();
+    let mut force_close = ForceClose::new(&fixture, ADMIN_KEYS[0]);
+    let asset_state_lamports = force_close.account(&force_close.asset_state_pda).lamports;
+
+    let invoked = force_close.process(&fixture.program_id, 1).unwrap();
+
+    // one plain transfer of the whole chips_due to the clock in wallet's ATA
+    let wallet_pickle_ata = get_associated_token_address(&fixture.wallet.pubkey(), &fixture.pickle_mint);
+    let expected = spl_token::instruction::transfer(
+        &spl_token::id(),
+        &fixture.mcpay_vault_pickle_ata,
+        &wallet_pickle_ata,
+        &fixture.mcpay_vault_pda,
+        &[],
+        LEVEL_ONE_RATE,
+    ).unwrap();
+    assert_eq!(invoked, vec![expected]);
+
+    let program_state = ProgramState::unpack(&force_close.account(&force_close.program_state_pda).data).unwrap();
+    assert_eq!(program_state.total_burned, 0);
+    assert_eq!(program_state.total_vesting_chips, CHIPS);
+    assert_eq!(program_state.assets_clocked_in, 0);
+
+    // the rent payer is the clock in wallet
+    assert_eq!(force_close.account(&force_close.asset_state_pda).lamports, 0);
+    assert_eq!(force_close.accounts[9].3.lamports, asset_state_lamports);
+}
+
+#[test]
+fn unpaid_force_close_only_closes_the_asset_state() {
+    let fixture = Fixture::new();
+    let mut force_close = ForceClose::new(&fixture, ADMIN_KEYS[0]);
+
+    let invoked = force_close.process(&fixture.program_id, 0).unwrap();
+    assert!(invoked.is_empty());
+    assert_eq!(force_close.account(&force_close.asset_state_pda).lamports, 0);
+
+    let program_state = ProgramState::unpack(&force_close.account(&force_close.program_state_pda).data).unwrap();
+    assert_eq!(program_state.assets_clocked_in, 0);
+}
+
+#[test]
+fn force_close_needs_an_admin() {
+    let fixture = Fixture::new();
+    let mut force_close = ForceClose::new(&fixture, Pubkey::new_unique());
+
+    assert_eq!(force_close.process(&fixture.program_id, 1), Err(ProgramError::MissingRequiredSignature));
+    assert!(AssetState::unpack(&force_close.account(&force_close.asset_state_pda).data).is_ok());
+}
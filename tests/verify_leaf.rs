@@ -0,0 +1,153 @@
+// leaf hashes and verify_leaf payloads shared with Bubblegum and the compression programs
+//
+// cargo test --test verify_leaf
+
+use borsh::BorshDeserialize;
+use mcpay_0::state::{
+    ClockInData,
+    ClockOutData,
+    LeafV2Data,
+    VERIFY_LEAF_DISCRIMINATOR,
+};
+use solana_program::{hash::hash, keccak, pubkey::Pubkey};
+
+struct Leaf {
+    id: Pubkey,
+    owner: Pubkey,
+    delegate: Pubkey,
+    nonce: u64,
+    data_hash: Pubkey,
+    creator_hash: Pubkey,
+}
+
+impl Leaf {
+    fn new() -> Self {
+        Self {
+            id: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            delegate: Pubkey::new_unique(),
+            nonce: 1_234,
+            data_hash: Pubkey::new_unique(),
+            creator_hash: Pubkey::new_unique(),
+        }
+    }
+
+    // version | id | owner | delegate | nonce | data hash | creator hash, the part V1 and V2 share
+    fn preimage(&self, version: u8) -> Vec<u8> {
+        let mut preimage = vec![version];
+        preimage.extend_from_slice(self.id.as_ref());
+        preimage.extend_from_slice(self.owner.as_ref());
+        preimage.extend_from_slice(self.delegate.as_ref());
+        preimage.extend_from_slice(&self.nonce.to_le_bytes());
+        preimage.extend_from_slice(self.data_hash.as_ref());
+        preimage.extend_from_slice(self.creator_hash.as_ref());
+        preimage
+    }
+}
+
+fn leaf_v2_data() -> LeafV2Data {
+    LeafV2Data {
+        collection_hash: Pubkey::new_unique(),
+        asset_data_hash: Pubkey::new_unique(),
+        flags: 1,
+    }
+}
+
+#[test]
+fn verify_leaf_discriminator() {
+    assert_eq!(VERIFY_LEAF_DISCRIMINATOR, hash(b"global:verify_leaf").to_bytes()[..8]);
+}
+
+#[test]
+fn shared_preimage_matches_bubblegum_v1() {
+    let leaf = Leaf::new();
+    let bubblegum_leaf = mpl_bubblegum::types::LeafSchema::V1 {
+        id: leaf.id,
+        owner: leaf.owner,
+        delegate: leaf.delegate,
+        nonce: leaf.nonce,
+        data_hash: leaf.data_hash.to_bytes(),
+        creator_hash: leaf.creator_hash.to_bytes(),
+    };
+    assert_eq!(keccak::hash(&leaf.preimage(1)).to_bytes(), bubblegum_leaf.hash());
+}
+
+// LeafSchema::V2 { id: [1; 32], owner: [2; 32], delegate: [3; 32], nonce: 1234, data_hash: [4; 32],
+// creator_hash: [5; 32], collection_hash: [6; 32], asset_data_hash: [7; 32], flags: 1 }.hash()
+// from mpl-bubblegum 2.1.1
+const BUBBLEGUM_V2_LEAF_HASH: [u8; 32] = [
+    61, 62, 222, 189, 26, 210, 131, 237, 165, 107, 216, 191, 197, 146, 132, 56,
+    190, 5, 83, 166, 148, 95, 163, 72, 18, 105, 9, 85, 166, 4, 241, 213,
+];
+
+#[test]
+fn leaf_v2_hash_matches_bubblegum_v2() {
+    let leaf_v2 = LeafV2Data {
+        collection_hash: Pubkey::new_from_array([6; 32]),
+        asset_data_hash: Pubkey::new_from_array([7; 32]),
+        flags: 1,
+    };
+
+    assert_eq!(
+        leaf_v2.leaf_hash(
+            &Pubkey::new_from_array([1; 32]),
+            &Pubkey::new_from_array([2; 32]),
+            &Pubkey::new_from_array([3; 32]),
+            1_234,
+            &Pubkey::new_from_array([4; 32]),
+            &Pubkey::new_from_array([5; 32]),
+        ),
+        BUBBLEGUM_V2_LEAF_HASH,
+    );
+}
+
+#[test]
+fn clock_in_data_without_leaf_v2() {
+    let clock_in_data = ClockInData {
+        root: Pubkey::new_unique(),
+        data_hash: Pubkey::new_unique(),
+        creator_hash: Pubkey::new_unique(),
+        nonce: 7,
+        proof_length: 14,
+        level: 1,
+        referrer: Pubkey::default(),
+        payout_recipient: Pubkey::default(),
+        leaf_v2: Some(leaf_v2_data()),
+        asset_state_bump: 254,
+        asset_flag_bump: 253,
+    };
+    let payload = borsh::to_vec(&clock_in_data).unwrap();
+
+    // a V1 client stops before the option tag
+    let v1_payload = &payload[..payload.len() - 68];
+    let parsed = ClockInData::try_from_slice(v1_payload).unwrap();
+    assert_eq!(parsed, ClockInData { leaf_v2: None, asset_state_bump: 0, asset_flag_bump: 0, ..clock_in_data.clone() });
+
+    // a client that does not pass the bumps leaves the program to find them
+    let no_bump_payload = &payload[..payload.len() - 2];
+    let parsed = ClockInData::try_from_slice(no_bump_payload).unwrap();
+    assert_eq!(parsed, ClockInData { asset_state_bump: 0, asset_flag_bump: 0, ..clock_in_data.clone() });
+
+    let no_flag_bump_payload = &payload[..payload.len() - 1];
+    let parsed = ClockInData::try_from_slice(no_flag_bump_payload).unwrap();
+    assert_eq!(parsed, ClockInData { asset_flag_bump: 0, ..clock_in_data.clone() });
+
+    assert_eq!(ClockInData::try_from_slice(&payload).unwrap(), clock_in_data);
+}
+
+#[test]
+fn clock_out_data_without_leaf_v2() {
+    let clock_out_data = ClockOutData {
+        root: Pubkey::new_unique(),
+        data_hash: Pubkey::new_unique(),
+        creator_hash: Pubkey::new_unique(),
+        nonce: 7,
+        proof_length: 14,
+        leaf_v2: None,
+    };
+    let payload = borsh::to_vec(&clock_out_data).unwrap();
+
+    let v1_payload = &payload[..payload.len() - 1];
+    assert_eq!(ClockOutData::try_from_slice(v1_payload).unwrap(), clock_out_data);
+    assert_eq!(ClockOutData::try_from_slice(&payload).unwrap(), clock_out_data);
+}